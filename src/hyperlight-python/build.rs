@@ -1,6 +1,17 @@
 use std::path::{Path, PathBuf};
 use std::{env, fs};
 
+/// Runtime feature profiles forwarded to the python-host build
+const RUNTIME_PROFILES: &[&str] = &[
+    "profile-minimal",
+    "profile-core",
+    "profile-extra",
+    "profile-full",
+];
+
+/// Environment variable naming the manifest of the Python modules frozen into the guest
+const FROZEN_MANIFEST_ENV: &str = "HYPERLIGHT_PYTHON_FROZEN";
+
 fn main() {
     let out_dir = env::var_os("OUT_DIR").unwrap();
     let dest_path = Path::new(&out_dir).join("host_resource.rs");
@@ -99,6 +110,15 @@ fn build_python_runtime() -> PathBuf {
         .env_clear_cargo()
         .env("HYPERLIGHT_CFLAGS", cflags);
 
+    let features = runtime_profile_features();
+    if !features.is_empty() {
+        cmd.arg("--features").arg(features.join(","));
+    }
+
+    if let Some(manifest) = frozen_manifest() {
+        cmd.env(FROZEN_MANIFEST_ENV, manifest);
+    }
+
     cmd.status()
         .unwrap_or_else(|e| panic!("Could not run cargo build python runtime: {e:?}\n{cmd:?}"));
 
@@ -123,13 +143,70 @@ fn build_python_runtime() -> PathBuf {
     }
 }
 
-fn bundle_host() {
-    let python_runtime_resource = build_python_runtime();
+/// The runtime feature profiles enabled on this crate
+fn runtime_profile_features() -> Vec<&'static str> {
+    RUNTIME_PROFILES
+        .iter()
+        .copied()
+        .filter(|feature| {
+            let var = format!("CARGO_FEATURE_{}", feature.to_uppercase().replace('-', "_"));
+            env::var_os(var).is_some()
+        })
+        .collect()
+}
+
+/// Manifest of the Python modules and packages frozen into the guest, with absolute paths.
+/// The modules are listed by the manifest named by `HYPERLIGHT_PYTHON_FROZEN`, one path
+/// per line relative to the manifest. Cargo doesn't tell a build script which crate
+/// depends on it, so the consumer names its manifest rather than have it looked up.
+fn frozen_manifest() -> Option<PathBuf> {
+    println!("cargo::rerun-if-env-changed={FROZEN_MANIFEST_ENV}");
 
+    let manifest = PathBuf::from(env::var_os(FROZEN_MANIFEST_ENV)?);
+    if !manifest.is_absolute() {
+        panic!(
+            "{FROZEN_MANIFEST_ENV} must be an absolute path, e.g. set with `relative = true` in .cargo/config.toml: {manifest:?}"
+        );
+    }
+    println!("cargo::rerun-if-changed={}", manifest.display());
+
+    let text = fs::read_to_string(&manifest)
+        .unwrap_or_else(|e| panic!("Could not read the frozen modules manifest {manifest:?}: {e}"));
+    let base_dir = manifest.parent().unwrap();
+    let paths: Vec<PathBuf> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| base_dir.join(line))
+        .collect();
+    for path in &paths {
+        println!("cargo::rerun-if-changed={}", path.display());
+    }
+
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    let manifest = out_dir.join("frozen.txt");
+    let contents: String = paths
+        .iter()
+        .map(|path| format!("{}\n", path.display()))
+        .collect();
+    fs::write(&manifest, contents).unwrap();
+
+    Some(manifest)
+}
+
+fn bundle_host() {
     let out_dir = env::var_os("OUT_DIR").unwrap();
     let dest_path = Path::new(&out_dir).join("host_resource.rs");
-    let contents =
-        format!("pub (super) static PYHOST: &[u8] = include_bytes!({python_runtime_resource:?});");
+
+    // With the custom-guest feature, sandboxes run the guest binary given to the builder
+    let contents = if env::var_os("CARGO_FEATURE_CUSTOM_GUEST").is_some() {
+        "pub (super) static PYHOST: Option<&[u8]> = None;".to_string()
+    } else {
+        let python_runtime_resource = build_python_runtime();
+        format!(
+            "pub (super) static PYHOST: Option<&[u8]> = Some(include_bytes!({python_runtime_resource:?}));"
+        )
+    };
 
     fs::write(dest_path, contents).unwrap();
     println!("cargo:rerun-if-changed=build.rs");
@@ -0,0 +1,301 @@
+use std::pin::Pin;
+use std::sync::{Arc, PoisonError};
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use hyperlight_host::hypervisor::InterruptHandle;
+use hyperlight_host::{Result, new_error};
+use tokio::sync::{Mutex, mpsc};
+
+use crate::HostPrintFn;
+use crate::sandbox::clock::Clock;
+use crate::sandbox::{ExecOptions, ExecStats, LoadedPySandbox, PyValue, SandboxBuilder};
+
+/// Asynchronous wrapper around a [`LoadedPySandbox`], available with the `tokio` feature.
+/// Scripts run on the tokio blocking thread pool, one at a time.
+/// Dropping a future before it completes cancels the script through the sandbox
+/// interrupt handle, which poisons the sandbox; set a
+/// [`RecoveryPolicy`](crate::sandbox::RecoveryPolicy) on the builder to heal it
+/// before the next run.
+///
+/// # Example
+/// ```
+/// use hyperlight_python::sandbox::{AsyncLoadedPySandbox, SandboxBuilder};
+///
+/// #[tokio::main]
+/// async fn main() -> hyperlight_host::Result<()> {
+///     let sandbox = AsyncLoadedPySandbox::new(SandboxBuilder::new()).await?;
+///
+///     let success = sandbox.run_script("print('Hello from Python!')".to_string()).await?;
+///     assert!(success);
+///     Ok(())
+/// }
+/// ```
+pub struct AsyncLoadedPySandbox {
+    /// The wrapped sandbox, locked for the duration of a run
+    inner: Arc<Mutex<LoadedPySandbox>>,
+    /// Handle for cancelling the running script
+    interrupt_handle: Arc<dyn InterruptHandle>,
+    /// Clock of the sandbox, interrupted to cut the sleep of a cancelled script short
+    clock: Clock,
+    /// Output of the scripts, until taken by [`AsyncLoadedPySandbox::output`]
+    output: Option<OutputStream>,
+}
+
+impl AsyncLoadedPySandbox {
+    /// Build a sandbox and load the Python runtime into it on the blocking thread pool.
+    /// The output of the scripts is available from [`AsyncLoadedPySandbox::output`],
+    /// and is still passed to the host print function of the builder if one is set.
+    /// # Arguments
+    /// * `builder` - The builder configuring the sandbox
+    /// # Errors
+    /// Returns an error if the sandbox could not be built or the Python runtime loaded.
+    pub async fn new(mut builder: SandboxBuilder) -> Result<Self> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let forward = builder.host_print_fn.take();
+        let print_fn: HostPrintFn = (move |msg: String| {
+            let len = msg.len() as i32;
+            let _ = sender.send(msg.clone());
+
+            match &forward {
+                Some(forward) => forward.call((msg,)),
+                None => Ok(len),
+            }
+        })
+        .into();
+        let builder = builder.with_host_print_fn(print_fn);
+
+        let sandbox = tokio::task::spawn_blocking(move || -> Result<LoadedPySandbox> {
+            let sandbox = builder.build()?.load_runtime()?;
+            Ok(sandbox.get_loaded_sandbox()?)
+        })
+        .await
+        .map_err(|e| new_error!("Could not load Python runtime: {:?}", e))??;
+
+        Ok(Self {
+            interrupt_handle: sandbox.interrupt_handle(),
+            clock: sandbox.clock(),
+            inner: Arc::new(Mutex::new(sandbox)),
+            output: Some(OutputStream { receiver }),
+        })
+    }
+
+    /// Take the stream of the output printed by the scripts.
+    /// Returns `None` if the stream was already taken.
+    ///
+    /// # Example
+    /// ```
+    /// use hyperlight_python::sandbox::{AsyncLoadedPySandbox, SandboxBuilder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> hyperlight_host::Result<()> {
+    ///     let mut sandbox = AsyncLoadedPySandbox::new(SandboxBuilder::new()).await?;
+    ///     let mut output = sandbox.output().expect("output already taken");
+    ///
+    ///     sandbox.run_script("print('Hello')".to_string()).await?;
+    ///     let printed = output.recv().await;
+    ///     assert!(printed.is_some_and(|s| s.starts_with("Hello")));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn output(&mut self) -> Option<OutputStream> {
+        self.output.take()
+    }
+
+    /// Returns whether the sandbox is poisoned.
+    /// See [`LoadedPySandbox::poisoned`].
+    pub async fn poisoned(&self) -> bool {
+        self.inner.lock().await.poisoned()
+    }
+
+    /// Run a Python script in the sandbox.
+    /// See [`LoadedPySandbox::run_script`].
+    /// # Arguments
+    /// * `code` - The Python code to execute as a string
+    pub async fn run_script(&self, code: String) -> Result<bool> {
+        self.run(move |sandbox| sandbox.run_script(code)).await
+    }
+
+    /// Run a Python script in the sandbox with the given [`ExecOptions`]
+    /// and report the resources it used.
+    /// See [`LoadedPySandbox::run_script_with_options`].
+    /// # Arguments
+    /// * `code` - The Python code to execute as a string
+    /// * `options` - The options for this run
+    pub async fn run_script_with_options(
+        &self,
+        code: String,
+        options: ExecOptions,
+    ) -> Result<(bool, ExecStats)> {
+        self.run(move |sandbox| sandbox.run_script_with_options(code, &options))
+            .await
+    }
+
+    /// Evaluate a Python expression in the sandbox and return its value.
+    /// See [`LoadedPySandbox::eval`].
+    /// # Arguments
+    /// * `code` - The Python expression to evaluate
+    pub async fn eval(&self, code: String) -> Result<PyValue> {
+        self.run(move |sandbox| sandbox.eval(&code)).await
+    }
+
+    /// Call a function defined by a previous script and return its value.
+    /// See [`LoadedPySandbox::call_function`].
+    /// # Arguments
+    /// * `name` - Name of the function
+    /// * `args` - The positional arguments of the call
+    pub async fn call_function(&self, name: String, args: Vec<PyValue>) -> Result<PyValue> {
+        self.run(move |sandbox| sandbox.call_function(&name, &args))
+            .await
+    }
+
+    /// Decode a JSON document in the sandbox and store it as a global.
+    /// See [`LoadedPySandbox::set_global_json`].
+    /// # Arguments
+    /// * `name` - Name of the global
+    /// * `value` - The JSON document
+    pub async fn set_global_json(&self, name: String, value: serde_json::Value) -> Result<()> {
+        self.run(move |sandbox| sandbox.set_global_json(&name, &value))
+            .await
+    }
+
+    /// Evaluate a Python expression in the sandbox and return its value as JSON.
+    /// See [`LoadedPySandbox::eval_json`].
+    /// # Arguments
+    /// * `code` - The Python expression to evaluate
+    pub async fn eval_json(&self, code: String) -> Result<serde_json::Value> {
+        self.run(move |sandbox| sandbox.eval_json(&code)).await
+    }
+
+    /// Run `f` on the sandbox on the blocking thread pool once the previous runs completed.
+    /// The run is cancelled if the returned future is dropped before it completes.
+    async fn run<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut LoadedPySandbox) -> Result<T> + Send + 'static,
+    {
+        let mut sandbox = self.inner.clone().lock_owned().await;
+        // The previous run completed once the sandbox is locked, its cancellation is over
+        self.clock.resume();
+        let cancel = CancelOnDrop::new(self.interrupt_handle.clone(), self.clock.clone());
+        let state = cancel.state.clone();
+
+        let result = tokio::task::spawn_blocking(move || {
+            if !RunState::start(&state) {
+                return Err(new_error!("Python run cancelled before it started"));
+            }
+
+            let result = f(&mut sandbox);
+            RunState::finish(&state);
+            result
+        })
+        .await;
+        cancel.disarm();
+
+        result.map_err(|e| new_error!("Python run failed: {:?}", e))?
+    }
+}
+
+/// Progress of a run, shared between its future and the blocking task running it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RunState {
+    /// The blocking task didn't start the run yet
+    Pending,
+    /// The run is calling into the guest
+    Running,
+    /// The run completed, the sandbox is free for the next one
+    Finished,
+    /// The future was dropped before the run started, it is skipped
+    Cancelled,
+}
+
+impl RunState {
+    /// Start the run unless it was cancelled. Returns whether it started.
+    fn start(state: &std::sync::Mutex<RunState>) -> bool {
+        let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+        if *state == RunState::Cancelled {
+            return false;
+        }
+
+        *state = RunState::Running;
+        true
+    }
+
+    /// Mark the run finished, so that dropping its future no longer cancels anything
+    fn finish(state: &std::sync::Mutex<RunState>) {
+        *state.lock().unwrap_or_else(PoisonError::into_inner) = RunState::Finished;
+    }
+}
+
+/// Cancels the running script when dropped, unless disarmed once the run completed
+struct CancelOnDrop {
+    /// Handle for cancelling the running script
+    interrupt_handle: Option<Arc<dyn InterruptHandle>>,
+    /// Clock of the sandbox, whose real sleeps return early once interrupted
+    clock: Clock,
+    /// Progress of the run: the sandbox is only interrupted while the run is in flight,
+    /// so that a late drop can't interrupt the next run on the same sandbox
+    state: Arc<std::sync::Mutex<RunState>>,
+}
+
+impl CancelOnDrop {
+    /// Create a new [`CancelOnDrop`]
+    /// # Arguments
+    /// * `interrupt_handle` - Handle for cancelling the running script
+    /// * `clock` - Clock of the sandbox
+    fn new(interrupt_handle: Arc<dyn InterruptHandle>, clock: Clock) -> Self {
+        Self {
+            interrupt_handle: Some(interrupt_handle),
+            clock,
+            state: Arc::new(std::sync::Mutex::new(RunState::Pending)),
+        }
+    }
+
+    /// Keep the run from being cancelled on drop
+    fn disarm(mut self) {
+        self.interrupt_handle = None;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        let Some(interrupt_handle) = self.interrupt_handle.take() else {
+            return;
+        };
+
+        // Held while killing so the run can't finish, and the next one start, meanwhile
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        match *state {
+            RunState::Pending => *state = RunState::Cancelled,
+            RunState::Running => {
+                // A script blocked in `time.sleep` only sees the kill once the sleep returns
+                self.clock.interrupt();
+                interrupt_handle.kill();
+            }
+            RunState::Finished | RunState::Cancelled => {}
+        }
+    }
+}
+
+/// Stream of the output printed by the scripts of an [`AsyncLoadedPySandbox`].
+/// Ends once the sandbox is dropped.
+pub struct OutputStream {
+    /// Output sent by the host print function
+    receiver: mpsc::UnboundedReceiver<String>,
+}
+
+impl OutputStream {
+    /// Receive the next piece of output.
+    /// Returns `None` once the sandbox is dropped and all the output was received.
+    pub async fn recv(&mut self) -> Option<String> {
+        self.receiver.recv().await
+    }
+}
+
+impl Stream for OutputStream {
+    type Item = String;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
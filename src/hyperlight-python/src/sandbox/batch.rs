@@ -0,0 +1,149 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+
+use hyperlight_host::{Result, new_error};
+
+use crate::HostPrintFn;
+use crate::sandbox::{ExecStats, LoadedPySandbox, SandboxBuilder};
+
+/// Result of a script run by [`SandboxBuilder::run_batch`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchResult {
+    /// Whether the script ran to completion
+    pub success: bool,
+    /// Output printed by the script, including the traceback of the exception it raised
+    pub output: String,
+    /// Text of the exception raised by the script, if any
+    pub exception: Option<String>,
+    /// Resources used by the script
+    pub stats: ExecStats,
+}
+
+/// Sandbox of a batch worker, with the output it captures
+struct Worker {
+    /// The sandbox running the scripts
+    sandbox: LoadedPySandbox,
+    /// Output printed by the script currently running
+    output: Arc<Mutex<String>>,
+}
+
+impl Worker {
+    /// Build a sandbox capturing its output
+    /// # Arguments
+    /// * `builder` - The builder configuring the sandbox
+    fn new(builder: &SandboxBuilder) -> Result<Self> {
+        let output = Arc::new(Mutex::new(String::new()));
+        let captured = output.clone();
+        let print_fn: HostPrintFn = (move |msg: String| {
+            let len = msg.len() as i32;
+            captured
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push_str(&msg);
+
+            Ok(len)
+        })
+        .into();
+
+        let proto_sbox = builder.clone().with_host_print_fn(print_fn).build()?;
+        let sandbox = proto_sbox.load_runtime()?.get_loaded_sandbox()?;
+
+        Ok(Self { sandbox, output })
+    }
+
+    /// Run a script from the state right after the Python runtime was loaded
+    /// # Arguments
+    /// * `code` - The Python code to execute
+    fn run(&mut self, code: String) -> Result<BatchResult> {
+        self.sandbox.restore_loaded()?;
+        self.output
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+
+        let (success, stats) = self.sandbox.run_script_with_stats(code)?;
+        let exception = if success {
+            None
+        } else {
+            self.sandbox.last_exception()?
+        };
+        let output =
+            std::mem::take(&mut *self.output.lock().unwrap_or_else(PoisonError::into_inner));
+
+        Ok(BatchResult {
+            success,
+            output,
+            exception,
+            stats,
+        })
+    }
+}
+
+/// Run independent scripts across `parallelism` sandboxes built by `builder`,
+/// each on its own thread. Every script starts from a freshly loaded runtime.
+/// See [`SandboxBuilder::run_batch`].
+pub(super) fn run_batch(
+    builder: &SandboxBuilder,
+    scripts: Vec<String>,
+    parallelism: usize,
+) -> Result<Vec<Result<BatchResult>>> {
+    let parallelism = parallelism.clamp(1, scripts.len().max(1));
+
+    let workers = thread::scope(|scope| {
+        let handles: Vec<_> = (0..parallelism)
+            .map(|_| scope.spawn(|| Worker::new(builder)))
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err(new_error!("Batch worker panicked")))
+            })
+            .collect::<Result<Vec<_>>>()
+    })?;
+
+    let results: Vec<_> = scripts.iter().map(|_| Mutex::new(None)).collect();
+    let next = AtomicUsize::new(0);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = workers
+            .into_iter()
+            .map(|mut worker| {
+                let (scripts, results, next) = (&scripts, &results, &next);
+
+                scope.spawn(move || {
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(code) = scripts.get(index) else {
+                            break;
+                        };
+
+                        let result = worker.run(code.clone());
+                        *results[index]
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner) = Some(result);
+                    }
+                })
+            })
+            .collect();
+
+        // A panicked worker leaves the result of its script unset, reported below,
+        // while the other workers run the remaining scripts
+        for handle in handles {
+            let _ = handle.join();
+        }
+    });
+
+    Ok(results
+        .into_iter()
+        .map(|result| {
+            result
+                .into_inner()
+                .unwrap_or_else(PoisonError::into_inner)
+                .unwrap_or_else(|| Err(new_error!("Batch worker panicked")))
+        })
+        .collect())
+}
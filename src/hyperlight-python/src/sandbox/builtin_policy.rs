@@ -0,0 +1,127 @@
+use hyperlight_host::{MultiUseSandbox, Result, new_error};
+
+/// Builtins restricted by [`BuiltinPolicy::restricted`]
+const RESTRICTED_BUILTINS: [&str; 6] = [
+    "eval",
+    "exec",
+    "compile",
+    "globals",
+    "setattr",
+    "__import__",
+];
+
+/// Policy restricting the builtins available to scripts in the sandbox.
+/// The guest applies it right after the Python runtime is initialized by replacing
+/// entries of the builtins module:
+/// - denied builtins raise a `PermissionError` naming the policy when called,
+/// - removed builtins raise `NameError: name '<name>' is not defined` when called,
+///   as CPython does for an undefined name.
+///
+/// The names of restricted builtins still resolve, e.g. `f = eval` succeeds:
+/// MicroPython's builtins are in ROM and can only be shadowed, not deleted.
+/// Only the `builtins` module would list them, and it is blocked.
+///
+/// `__import__` can be restricted too: its direct calls raise, while `import`
+/// statements keep working, subject to the module policy.
+///
+/// Once a policy is applied, the `builtins` module can no longer be imported so that
+/// scripts cannot restore the original entries.
+///
+/// # Example
+/// ```
+/// use hyperlight_python::sandbox::{BuiltinPolicy, SandboxBuilder};
+///
+/// fn main() -> hyperlight_host::Result<()> {
+///     let policy = BuiltinPolicy::new("tier-free")
+///         .deny("eval")
+///         .deny("exec")
+///         .remove("compile");
+///
+///     let sandbox = SandboxBuilder::new()
+///         .with_builtin_policy(policy)
+///         .build()?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuiltinPolicy {
+    /// Name of the policy, reported by denied builtins
+    name: String,
+    /// Builtins raising a `PermissionError`
+    denied: Vec<String>,
+    /// Builtins raising a `NameError` when called
+    removed: Vec<String>,
+}
+
+impl BuiltinPolicy {
+    /// Create an empty [`BuiltinPolicy`]
+    /// # Arguments
+    /// * `name` - Name of the policy, reported by denied builtins
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            denied: Vec::new(),
+            removed: Vec::new(),
+        }
+    }
+
+    /// Create a policy named `restricted` that denies `eval`, `exec`, `compile`,
+    /// `globals`, `setattr` and direct calls to `__import__`.
+    ///
+    /// `import` statements keep working; use
+    /// [`SandboxBuilder::allow_modules`](crate::sandbox::SandboxBuilder::allow_modules)
+    /// or [`SandboxBuilder::deny_modules`](crate::sandbox::SandboxBuilder::deny_modules)
+    /// to control which modules they import.
+    pub fn restricted() -> Self {
+        RESTRICTED_BUILTINS
+            .iter()
+            .fold(Self::new("restricted"), |policy, builtin| {
+                policy.deny(*builtin)
+            })
+    }
+
+    /// Make a builtin raise a `PermissionError` naming the policy when called
+    /// # Arguments
+    /// * `builtin` - Name of the builtin
+    pub fn deny(mut self, builtin: impl Into<String>) -> Self {
+        self.denied.push(builtin.into());
+
+        self
+    }
+
+    /// Make a builtin raise a `NameError` when called.
+    /// The name still resolves, see [`BuiltinPolicy`].
+    /// # Arguments
+    /// * `builtin` - Name of the builtin
+    pub fn remove(mut self, builtin: impl Into<String>) -> Self {
+        self.removed.push(builtin.into());
+
+        self
+    }
+
+    /// Name of the policy
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Send the policy to the guest.
+    /// Must be called after the Python runtime is initialized.
+    /// # Arguments
+    /// * `sandbox` - The sandbox with the Python runtime initialized
+    pub(crate) fn apply(&self, sandbox: &mut MultiUseSandbox) -> Result<()> {
+        let applied = sandbox.call::<bool>(
+            "set_builtin_policy",
+            (
+                self.name.clone(),
+                self.denied.join("\n"),
+                self.removed.join("\n"),
+            ),
+        )?;
+
+        if !applied {
+            return Err(new_error!("Guest rejected the builtin policy {:?}", self));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,203 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Start of the virtual clock of deterministic sandboxes (2000-01-01T00:00:00Z)
+pub(crate) const DETERMINISTIC_START_SECS: u64 = 946_684_800;
+
+/// Longest a real sleep blocks before checking whether the run was cancelled
+const SLEEP_SLICE: Duration = Duration::from_millis(10);
+
+/// Clock read by the `time` module of the sandbox.
+/// Used with [`SandboxBuilder::with_clock_policy`](crate::sandbox::SandboxBuilder::with_clock_policy).
+///
+/// # Example
+/// ```
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// use hyperlight_python::sandbox::ClockPolicy;
+///
+/// // 2024-01-01T00:00:00Z
+/// let policy = ClockPolicy::Virtual(UNIX_EPOCH + Duration::from_secs(1_704_067_200));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ClockPolicy {
+    /// The host clock. `sleep` blocks the sandbox for the given time,
+    /// unless the run is cancelled by dropping the future of an
+    /// [`AsyncLoadedPySandbox`](crate::sandbox::AsyncLoadedPySandbox).
+    #[default]
+    Real,
+    /// Time stands still at the given instant and `sleep` returns immediately
+    Frozen(SystemTime),
+    /// Time starts at the given instant and only advances with `sleep`,
+    /// which returns immediately
+    Virtual(SystemTime),
+}
+
+/// Clock backing the host functions called by the `time` module.
+/// Clones share the time slept on a virtual clock, so that it keeps advancing
+/// across the sandboxes rebuilt from the same factory.
+#[derive(Debug, Clone)]
+pub(crate) struct Clock {
+    /// The clock policy
+    policy: ClockPolicy,
+    /// Origin of the monotonic ticks of a real clock
+    start: Instant,
+    /// Time slept on a virtual clock
+    slept: Arc<Mutex<Duration>>,
+    /// Set when the run is cancelled, to cut a real sleep short
+    interrupted: Arc<AtomicBool>,
+}
+
+impl Clock {
+    /// Create a new [`Clock`]
+    /// # Arguments
+    /// * `policy` - The clock policy
+    pub(crate) fn new(policy: ClockPolicy) -> Self {
+        Self {
+            policy,
+            start: Instant::now(),
+            slept: Arc::new(Mutex::new(Duration::ZERO)),
+            interrupted: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Nanoseconds since the Unix epoch, for `time.time()` and `time.time_ns()`
+    pub(crate) fn time_ns(&self) -> u64 {
+        let now = match self.policy {
+            ClockPolicy::Real => SystemTime::now(),
+            ClockPolicy::Frozen(instant) => instant,
+            ClockPolicy::Virtual(start) => start + self.slept(),
+        };
+
+        nanos(now.duration_since(UNIX_EPOCH).unwrap_or_default())
+    }
+
+    /// Nanoseconds of the monotonic clock, for `time.ticks_ms()` and friends
+    pub(crate) fn ticks_ns(&self) -> u64 {
+        nanos(match self.policy {
+            ClockPolicy::Real => self.start.elapsed(),
+            ClockPolicy::Frozen(_) => Duration::ZERO,
+            ClockPolicy::Virtual(_) => self.slept(),
+        })
+    }
+
+    /// Sleep for `ns` nanoseconds, for `time.sleep()` and friends.
+    /// A real sleep is taken in slices and returns early once the clock is interrupted.
+    pub(crate) fn sleep_ns(&self, ns: u64) {
+        let duration = Duration::from_nanos(ns);
+
+        match self.policy {
+            ClockPolicy::Real => {
+                let start = Instant::now();
+                while !self.interrupted.load(Ordering::Acquire) {
+                    let left = duration.saturating_sub(start.elapsed());
+                    if left.is_zero() {
+                        break;
+                    }
+                    thread::sleep(left.min(SLEEP_SLICE));
+                }
+            }
+            ClockPolicy::Frozen(_) => {}
+            ClockPolicy::Virtual(_) => {
+                *self.slept.lock().unwrap_or_else(PoisonError::into_inner) += duration;
+            }
+        }
+    }
+
+    /// Cut the current and the following real sleeps short, when the run is cancelled
+    pub(crate) fn interrupt(&self) {
+        self.interrupted.store(true, Ordering::Release);
+    }
+
+    /// Let real sleeps last their full time again, before the next run
+    pub(crate) fn resume(&self) {
+        self.interrupted.store(false, Ordering::Release);
+    }
+
+    /// Rewind a virtual clock to its start, so that every deterministic run sees the same time
+    pub(crate) fn reset(&self) {
+        *self.slept.lock().unwrap_or_else(PoisonError::into_inner) = Duration::ZERO;
+    }
+
+    fn slept(&self) -> Duration {
+        *self.slept.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Whole nanoseconds of a duration, saturating at `u64::MAX`
+fn nanos(duration: Duration) -> u64 {
+    duration.as_nanos().try_into().unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn frozen_clock_stands_still() {
+        let clock = Clock::new(ClockPolicy::Frozen(at(60)));
+
+        clock.sleep_ns(1_000_000_000);
+        assert_eq!(clock.time_ns(), 60_000_000_000);
+        assert_eq!(clock.ticks_ns(), 0);
+    }
+
+    #[test]
+    fn virtual_clock_advances_with_sleep() {
+        let clock = Clock::new(ClockPolicy::Virtual(at(60)));
+        let rebuilt = clock.clone();
+
+        clock.sleep_ns(1_500_000_000);
+        assert_eq!(clock.time_ns(), 61_500_000_000);
+        assert_eq!(rebuilt.ticks_ns(), 1_500_000_000);
+
+        rebuilt.reset();
+        assert_eq!(clock.time_ns(), 60_000_000_000);
+        assert_eq!(clock.ticks_ns(), 0);
+    }
+
+    #[test]
+    fn real_clock_sleeps() {
+        let clock = Clock::new(ClockPolicy::Real);
+        let start = Instant::now();
+        let ticks = clock.ticks_ns();
+
+        clock.sleep_ns(25_000_000);
+        assert!(start.elapsed() >= Duration::from_millis(25));
+        assert!(clock.ticks_ns() - ticks >= 25_000_000);
+    }
+
+    #[test]
+    fn interrupt_cuts_a_real_sleep_short() {
+        let clock = Clock::new(ClockPolicy::Real);
+        let start = Instant::now();
+
+        let interrupter = clock.clone();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            interrupter.interrupt();
+        });
+        clock.sleep_ns(u64::MAX);
+        handle.join().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(10));
+
+        // Sleeps return immediately until the clock is resumed for the next run
+        clock.sleep_ns(u64::MAX);
+        clock.resume();
+        let start = Instant::now();
+        clock.sleep_ns(15_000_000);
+        assert!(start.elapsed() >= Duration::from_millis(15));
+    }
+
+    #[test]
+    fn nanos_saturate() {
+        assert_eq!(nanos(Duration::from_secs(1)), 1_000_000_000);
+        assert_eq!(nanos(Duration::MAX), u64::MAX);
+    }
+}
@@ -0,0 +1,180 @@
+use hyperlight_host::{MultiUseSandbox, Result, new_error};
+
+/// What happens when a script prints more than [`ExecOptions::max_output_bytes`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputLimitPolicy {
+    /// Drop the output beyond the limit and let the script run to completion
+    #[default]
+    TruncateAndContinue,
+    /// Drop the output beyond the limit and stop the script by raising `SystemExit`
+    TruncateAndAbort,
+    /// Stop the script by raising a `RuntimeError` and fail the run with an error
+    Error,
+}
+
+impl OutputLimitPolicy {
+    /// Policy value understood by the guest `set_output_limit` function
+    fn guest_value(self) -> i32 {
+        match self {
+            OutputLimitPolicy::TruncateAndContinue => 0,
+            OutputLimitPolicy::TruncateAndAbort => 1,
+            OutputLimitPolicy::Error => 2,
+        }
+    }
+}
+
+/// Options for a single script run.
+/// Used with [`LoadedPySandbox::run_script_with_options`](crate::sandbox::LoadedPySandbox::run_script_with_options).
+///
+/// # Example
+/// ```
+/// use hyperlight_python::sandbox::{ExecOptions, OutputLimitPolicy};
+///
+/// let options = ExecOptions::new()
+///     .max_output_bytes(64 * 1024)
+///     .output_limit_policy(OutputLimitPolicy::TruncateAndAbort)
+///     .seed(42);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ExecOptions {
+    /// Maximum number of bytes the script can print
+    max_output_bytes: Option<u64>,
+    /// What happens once the output limit is reached, if set
+    output_limit_policy: Option<OutputLimitPolicy>,
+    /// Seed of the `random` module for the run
+    seed: Option<u64>,
+}
+
+impl ExecOptions {
+    /// Create new [`ExecOptions`] without any limit
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the number of bytes the script can print.
+    /// The limit is enforced in the guest, so the output beyond it never reaches
+    /// the host print function.
+    /// # Arguments
+    /// * `max_bytes` - Maximum number of bytes the script can print
+    pub fn max_output_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_output_bytes = Some(max_bytes);
+
+        self
+    }
+
+    /// Set what happens when the output limit is reached.
+    /// Defaults to [`OutputLimitPolicy::TruncateAndContinue`].
+    /// Requires [`ExecOptions::max_output_bytes`]: a run with a policy but no limit fails.
+    /// # Arguments
+    /// * `policy` - The [`OutputLimitPolicy`] to apply
+    pub fn output_limit_policy(mut self, policy: OutputLimitPolicy) -> Self {
+        self.output_limit_policy = Some(policy);
+
+        self
+    }
+
+    /// Seed the `random` module before the run, so that the script draws the
+    /// same random numbers every time it runs with the same seed.
+    /// Without a seed, the module is seeded from the host RNG when first imported.
+    /// The MicroPython generator keeps a 32-bit state, so the high 32 bits of the seed are
+    /// folded into the low ones: seeds equal once folded give the same random numbers.
+    /// # Arguments
+    /// * `seed` - The seed of the `random` module
+    ///
+    /// # Example
+    /// ```
+    /// use hyperlight_python::sandbox::{ExecOptions, PyValue, SandboxBuilder};
+    ///
+    /// fn main() -> hyperlight_host::Result<()> {
+    ///     let proto_sbox = SandboxBuilder::new()
+    ///         .build()?;
+    ///
+    ///     let sandbox = proto_sbox.load_runtime()?;
+    ///     let mut sandbox = sandbox.get_loaded_sandbox()?;
+    ///
+    ///     let options = ExecOptions::new().seed(42);
+    ///     let code = "import random\nfirst = random.getrandbits(32)".to_string();
+    ///     sandbox.run_script_with_options(code, &options)?;
+    ///     let first = sandbox.eval("first")?;
+    ///
+    ///     let code = "second = random.getrandbits(32)".to_string();
+    ///     sandbox.run_script_with_options(code, &options)?;
+    ///     assert_eq!(sandbox.eval("second")?, first);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+
+        self
+    }
+
+    /// Whether the run fails with an error when the output limit is reached
+    pub(crate) fn fails_on_output_limit(&self) -> bool {
+        self.output_limit_policy == Some(OutputLimitPolicy::Error)
+    }
+
+    /// Maximum number of bytes the script can print, if limited
+    pub(crate) fn output_limit(&self) -> Option<u64> {
+        self.max_output_bytes
+    }
+
+    /// Check that the options are consistent, before the run starts
+    /// # Errors
+    /// Returns an error if an output limit policy is set without an output limit.
+    pub(crate) fn validate(&self) -> Result<()> {
+        match (self.max_output_bytes, self.output_limit_policy) {
+            (None, Some(policy)) => Err(new_error!(
+                "Output limit policy {:?} set without ExecOptions::max_output_bytes",
+                policy
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Send the options to the guest ahead of the next run
+    /// # Arguments
+    /// * `sandbox` - The sandbox with the Python runtime initialized
+    pub(crate) fn apply(&self, sandbox: &mut MultiUseSandbox) -> Result<()> {
+        if let Some(max_bytes) = self.max_output_bytes {
+            let policy = self.output_limit_policy.unwrap_or_default().guest_value();
+            if !sandbox.call::<bool>("set_output_limit", (max_bytes, policy))? {
+                return Err(new_error!(
+                    "Guest rejected the output limit policy {policy}"
+                ));
+            }
+        }
+
+        if let Some(seed) = self.seed
+            && !sandbox.call::<bool>("seed_random", seed)?
+        {
+            return Err(new_error!("Could not seed the random module"));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_limit_policy_requires_a_limit() {
+        assert!(ExecOptions::new().validate().is_ok());
+        assert!(ExecOptions::new().max_output_bytes(16).validate().is_ok());
+        assert!(
+            ExecOptions::new()
+                .max_output_bytes(16)
+                .output_limit_policy(OutputLimitPolicy::Error)
+                .validate()
+                .is_ok()
+        );
+
+        let err = ExecOptions::new()
+            .output_limit_policy(OutputLimitPolicy::TruncateAndAbort)
+            .validate()
+            .unwrap_err();
+        assert!(err.to_string().contains("max_output_bytes"), "{}", err);
+    }
+}
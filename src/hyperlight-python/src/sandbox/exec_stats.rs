@@ -0,0 +1,96 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use hyperlight_host::{Result, new_error};
+
+/// Number of counters reported by the guest `exec_stats` function
+const GUEST_COUNTERS: usize = 6;
+
+/// Resource usage of a single script run.
+/// Returned by [`LoadedPySandbox::run_script_with_stats`](crate::sandbox::LoadedPySandbox::run_script_with_stats).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExecStats {
+    /// Time elapsed on the host while the script was running
+    pub wall_time: Duration,
+    /// Time spent executing in the guest, i.e. the wall time minus the time
+    /// spent in host functions called by the guest
+    pub guest_cpu_time: Duration,
+    /// Highest number of bytes in use on the MicroPython GC heap
+    pub peak_heap_bytes: u64,
+    /// Number of garbage collections that ran
+    pub gc_collections: u64,
+    /// Number of bytes allocated on the MicroPython GC heap
+    pub bytes_allocated: u64,
+    /// Number of calls the guest made to the host
+    pub host_calls: u64,
+    /// Number of bytes printed by the script
+    pub bytes_printed: u64,
+    /// Whether output was dropped because of [`ExecOptions::max_output_bytes`](crate::sandbox::ExecOptions::max_output_bytes)
+    pub output_truncated: bool,
+}
+
+impl ExecStats {
+    /// Build the [`ExecStats`] from the counters returned by the guest and the host side timings
+    /// # Arguments
+    /// * `counters` - The little-endian `u64` counters returned by the `exec_stats` guest function
+    /// * `wall_time` - Time elapsed on the host while the script was running
+    /// * `host_time` - Time spent in host functions during the run
+    pub(crate) fn from_guest(
+        counters: &[u8],
+        wall_time: Duration,
+        host_time: Duration,
+    ) -> Result<Self> {
+        if counters.len() < GUEST_COUNTERS * 8 {
+            return Err(new_error!(
+                "Invalid execution stats from guest: expected {} bytes, got {}",
+                GUEST_COUNTERS * 8,
+                counters.len()
+            ));
+        }
+
+        let counter = |index: usize| {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&counters[index * 8..(index + 1) * 8]);
+            u64::from_le_bytes(bytes)
+        };
+
+        Ok(Self {
+            wall_time,
+            guest_cpu_time: wall_time.saturating_sub(host_time),
+            peak_heap_bytes: counter(0),
+            gc_collections: counter(1),
+            bytes_allocated: counter(2),
+            host_calls: counter(3),
+            bytes_printed: counter(4),
+            output_truncated: counter(5) != 0,
+        })
+    }
+}
+
+/// Accumulates the time spent in host functions called by the guest.
+/// Shared between the registered host functions and the sandbox running the script.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct HostCallTimer(Arc<AtomicU64>);
+
+impl HostCallTimer {
+    /// Run `f` and add the time it took to the accumulated host time
+    pub(crate) fn time<T>(&self, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        let elapsed = start.elapsed().as_nanos() as u64;
+        self.0.fetch_add(elapsed, Ordering::Relaxed);
+
+        result
+    }
+
+    /// Reset the accumulated host time
+    pub(crate) fn reset(&self) {
+        self.0.store(0, Ordering::Relaxed);
+    }
+
+    /// Accumulated host time since the last reset
+    pub(crate) fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.0.load(Ordering::Relaxed))
+    }
+}
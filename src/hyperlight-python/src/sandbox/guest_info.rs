@@ -0,0 +1,170 @@
+use std::fmt;
+
+use hyperlight_host::{MultiUseSandbox, Result, new_error};
+use serde_json::Value;
+
+/// Version of the interface between the host and the guest: the guest functions, their
+/// arguments and the encoding of their results. Must match `ABI_VERSION` of python-host.
+pub(crate) const GUEST_ABI_VERSION: u32 = 2;
+
+/// Description of the guest binary running the Python runtime, reported by its
+/// `abi_info` guest function, see [`PySandbox::guest_info`](crate::sandbox::PySandbox::guest_info)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuestInfo {
+    /// Version of the interface between the host and the guest
+    pub abi_version: u32,
+    /// Version of MicroPython, e.g. `1.27.0`
+    pub micropython_version: String,
+    /// Features the runtime was built with: the `profile-*` feature profile,
+    /// and `frozen` if Python modules are frozen into it
+    pub features: Vec<String>,
+    /// Names of the Python modules implemented in Rust
+    pub native_modules: Vec<String>,
+    /// Size of the MicroPython garbage collector heap, in bytes
+    pub heap_size: u64,
+    /// Stack space kept free below the recursion limit, in bytes
+    pub stack_check_margin: u64,
+}
+
+impl GuestInfo {
+    /// Ask the guest for its description
+    /// # Arguments
+    /// * `sandbox` - The sandbox running the guest, with or without the runtime initialized
+    pub(crate) fn query(sandbox: &mut MultiUseSandbox) -> Result<Self> {
+        let json = sandbox.call::<String>("abi_info", ()).map_err(|e| {
+            new_error!(
+                "Incompatible guest binary: it doesn't report its ABI info, expected ABI version {}: {:?}",
+                GUEST_ABI_VERSION,
+                e
+            )
+        })?;
+
+        Self::from_json(&json)
+    }
+
+    /// Parse the description returned by the `abi_info` guest function
+    /// # Arguments
+    /// * `json` - The JSON object returned by the guest
+    fn from_json(json: &str) -> Result<Self> {
+        let invalid = || new_error!("Invalid guest ABI info: {}", json);
+        let value: Value = serde_json::from_str(json).map_err(|_| invalid())?;
+
+        let number = |key: &str| value[key].as_u64().ok_or_else(invalid);
+        let strings = |key: &str| -> Result<Vec<String>> {
+            value[key]
+                .as_array()
+                .ok_or_else(invalid)?
+                .iter()
+                .map(|item| item.as_str().map(String::from).ok_or_else(invalid))
+                .collect()
+        };
+
+        Ok(Self {
+            abi_version: u32::try_from(number("abi_version")?).map_err(|_| invalid())?,
+            micropython_version: value["micropython_version"]
+                .as_str()
+                .ok_or_else(invalid)?
+                .to_string(),
+            features: strings("features")?,
+            native_modules: strings("native_modules")?,
+            heap_size: number("heap_size")?,
+            stack_check_margin: number("stack_check_margin")?,
+        })
+    }
+
+    /// Check that the guest implements the interface of this host
+    /// # Errors
+    /// Returns an incompatibility error describing the guest otherwise.
+    pub(crate) fn check(&self) -> Result<()> {
+        if self.abi_version != GUEST_ABI_VERSION {
+            return Err(new_error!(
+                "Incompatible guest binary: {}, expected ABI version {}",
+                self,
+                GUEST_ABI_VERSION
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for GuestInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ABI version {}, MicroPython {}, features [{}], native modules [{}], \
+             heap {} bytes, stack check margin {} bytes",
+            self.abi_version,
+            self.micropython_version,
+            self.features.join(", "),
+            self.native_modules.join(", "),
+            self.heap_size,
+            self.stack_check_margin
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Description returned by the `abi_info` guest function
+    const INFO: &str = r#"{"abi_version": 2, "micropython_version": "1.27.0", "features": ["profile-core", "frozen"], "native_modules": ["hashlib"], "heap_size": 32768, "stack_check_margin": 24576}"#;
+
+    #[test]
+    fn from_json_reads_the_guest_description() {
+        let info = GuestInfo::from_json(INFO).unwrap();
+
+        assert_eq!(
+            info,
+            GuestInfo {
+                abi_version: 2,
+                micropython_version: "1.27.0".to_string(),
+                features: vec!["profile-core".to_string(), "frozen".to_string()],
+                native_modules: vec!["hashlib".to_string()],
+                heap_size: 32768,
+                stack_check_margin: 24576,
+            }
+        );
+        assert!(info.check().is_ok());
+    }
+
+    #[test]
+    fn from_json_rejects_invalid_descriptions() {
+        let invalid = [
+            "",
+            "[]",
+            &INFO.replace("\"abi_version\": 2", "\"abi_version\": \"2\""),
+            &INFO.replace("\"abi_version\": 2", "\"abi_version\": 4294967296"),
+            &INFO.replace("\"heap_size\": 32768", "\"heap_size\": -1"),
+            &INFO.replace(
+                "\"micropython_version\": \"1.27.0\"",
+                "\"micropython_version\": 1",
+            ),
+            &INFO.replace("[\"hashlib\"]", "[1]"),
+            &INFO.replace(", \"stack_check_margin\": 24576", ""),
+        ];
+
+        for json in invalid {
+            assert!(GuestInfo::from_json(json).is_err(), "{}", json);
+        }
+    }
+
+    #[test]
+    fn check_rejects_another_abi_version() {
+        let json = INFO.replace("\"abi_version\": 2", "\"abi_version\": 1");
+        let err = GuestInfo::from_json(&json).unwrap().check().unwrap_err();
+
+        assert!(
+            err.to_string()
+                .contains("ABI version 1, MicroPython 1.27.0"),
+            "{}",
+            err
+        );
+        assert!(
+            err.to_string().contains("expected ABI version 2"),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,444 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use hyperlight_host::{Result, new_error};
+use serde_json::{Value, json};
+
+/// A call made by the guest to a host function during a script run, with its
+/// arguments and the response it got.
+/// Recorded by sandboxes built with
+/// [`SandboxBuilder::deterministic`](crate::sandbox::SandboxBuilder::deterministic) or
+/// [`SandboxBuilder::record_host_calls`](crate::sandbox::SandboxBuilder::record_host_calls),
+/// see [`LoadedPySandbox::host_calls`](crate::sandbox::LoadedPySandbox::host_calls).
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostCall {
+    /// Name of the host function, e.g. `HostPrint` or `HostTimeNs`
+    pub function: String,
+    /// Arguments of the call
+    pub args: Vec<Value>,
+    /// Response of the host function, `null` if it returns nothing
+    pub response: Value,
+}
+
+impl HostCall {
+    /// The call as a line of a recording:
+    /// `{"function": "HostTimeNs", "args": [], "response": 946684800000000000}`
+    pub fn to_json(&self) -> Value {
+        json!({
+            "function": self.function,
+            "args": self.args,
+            "response": self.response,
+        })
+    }
+
+    /// Parse a call from a line of a recording, see [`HostCall::to_json`]
+    /// # Arguments
+    /// * `value` - The JSON object of the call
+    pub fn from_json(value: &Value) -> Result<Self> {
+        let function = value["function"]
+            .as_str()
+            .ok_or_else(|| new_error!("Host call without a function name: {}", value))?;
+        let args = value["args"]
+            .as_array()
+            .ok_or_else(|| new_error!("Host call without arguments: {}", value))?;
+
+        Ok(Self {
+            function: function.to_string(),
+            args: args.clone(),
+            response: value["response"].clone(),
+        })
+    }
+}
+
+/// Host calls made by the guest: recorded for the current run if enabled,
+/// and answered from a recording in replay mode.
+/// Clones share the recorded calls and the replay position.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct HostCalls {
+    /// Calls of the current run, if recorded
+    recorded: Option<Arc<Mutex<Vec<HostCall>>>>,
+    /// File the calls of every run are written to
+    record_path: Option<PathBuf>,
+    /// Recording answering the calls in place of the host functions
+    replay: Option<HostCallReplay>,
+}
+
+impl HostCalls {
+    /// Create a new [`HostCalls`]
+    /// # Arguments
+    /// * `record` - Whether the calls are recorded
+    /// * `record_path` - File the calls of every run are written to, which implies `record`
+    /// * `replay` - Recording answering the calls in place of the host functions
+    pub(crate) fn new(
+        record: bool,
+        record_path: Option<PathBuf>,
+        replay: Option<HostCallReplay>,
+    ) -> Self {
+        Self {
+            recorded: (record || record_path.is_some()).then(Arc::default),
+            record_path,
+            replay,
+        }
+    }
+
+    /// Answer a host call: from the recording in replay mode, with `call` otherwise
+    /// # Arguments
+    /// * `function` - Name of the host function
+    /// * `args` - Arguments of the call
+    /// * `call` - The host function
+    pub(crate) fn call(
+        &self,
+        function: &str,
+        args: Vec<Value>,
+        call: impl FnOnce() -> Result<Value>,
+    ) -> Result<Value> {
+        let response = match &self.replay {
+            Some(replay) => replay.respond(function, &args)?,
+            None => call()?,
+        };
+
+        if let Some(recorded) = &self.recorded {
+            recorded
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(HostCall {
+                    function: function.to_string(),
+                    args,
+                    response: response.clone(),
+                });
+        }
+
+        Ok(response)
+    }
+
+    /// Forget the recorded calls and rewind the recording, at the start of a run
+    pub(crate) fn begin_run(&self) {
+        if let Some(recorded) = &self.recorded {
+            recorded
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clear();
+        }
+
+        if let Some(replay) = &self.replay {
+            replay.rewind();
+        }
+    }
+
+    /// Write the calls of the run that just ended to the record file, and check
+    /// that it made the recorded calls in replay mode
+    /// # Errors
+    /// Returns an error if the file couldn't be written or the run diverged from the recording.
+    pub(crate) fn end_run(&self) -> Result<()> {
+        if let Some(path) = &self.record_path {
+            save(&self.calls(), path)?;
+        }
+
+        match &self.replay {
+            Some(replay) => replay.finish(),
+            None => Ok(()),
+        }
+    }
+
+    /// The calls recorded since the start of the run
+    pub(crate) fn calls(&self) -> Vec<HostCall> {
+        self.recorded
+            .as_ref()
+            .map(|recorded| {
+                recorded
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .clone()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Write host calls as JSON lines
+fn save(calls: &[HostCall], path: &Path) -> Result<()> {
+    let lines: String = calls
+        .iter()
+        .map(|call| format!("{}\n", call.to_json()))
+        .collect();
+
+    fs::write(path, lines).map_err(|e| {
+        new_error!(
+            "Could not write the host calls to {}: {}",
+            path.display(),
+            e
+        )
+    })
+}
+
+/// A recording of host calls, answered in order in place of the host functions
+#[derive(Debug, Clone)]
+pub(crate) struct HostCallReplay {
+    /// The recorded calls
+    calls: Arc<Vec<HostCall>>,
+    /// Replay position of the current run
+    state: Arc<Mutex<ReplayState>>,
+}
+
+#[derive(Debug, Default)]
+struct ReplayState {
+    /// Index of the next call
+    next: usize,
+    /// First call of the run that didn't match the recording
+    divergence: Option<String>,
+}
+
+impl HostCallReplay {
+    /// Load a recording written by [`SandboxBuilder::record_host_calls`](crate::sandbox::SandboxBuilder::record_host_calls)
+    /// # Arguments
+    /// * `path` - The recording, one JSON object per line
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).map_err(|e| {
+            new_error!(
+                "Could not read the host calls from {}: {}",
+                path.display(),
+                e
+            )
+        })?;
+
+        let calls = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let value = serde_json::from_str(line)
+                    .map_err(|e| new_error!("Invalid host call in {}: {}", path.display(), e))?;
+                HostCall::from_json(&value)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self::new(calls))
+    }
+
+    /// Create a new [`HostCallReplay`]
+    /// # Arguments
+    /// * `calls` - The recorded calls, in order
+    fn new(calls: Vec<HostCall>) -> Self {
+        Self {
+            calls: Arc::new(calls),
+            state: Arc::default(),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, ReplayState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Replay the recording from its first call
+    fn rewind(&self) {
+        *self.state() = ReplayState::default();
+    }
+
+    /// Recorded response of the next call, which must match the recording
+    fn respond(&self, function: &str, args: &[Value]) -> Result<Value> {
+        let mut state = self.state();
+        if let Some(divergence) = &state.divergence {
+            return Err(new_error!("{}", divergence));
+        }
+
+        let index = state.next;
+        match self.calls.get(index) {
+            Some(call) if call.function == function && call.args == args => {
+                state.next += 1;
+                Ok(call.response.clone())
+            }
+            recorded => {
+                let divergence = format!(
+                    "host call #{} {}({}) doesn't match the recorded {}",
+                    index + 1,
+                    function,
+                    Value::from(args),
+                    recorded.map_or("end of the run".to_string(), |call| format!(
+                        "{}({})",
+                        call.function,
+                        Value::from(call.args.as_slice())
+                    )),
+                );
+                state.divergence = Some(divergence.clone());
+                Err(new_error!("{}", divergence))
+            }
+        }
+    }
+
+    /// Check that the run made all the recorded calls
+    fn finish(&self) -> Result<()> {
+        let state = self.state();
+
+        if let Some(divergence) = &state.divergence {
+            return Err(new_error!(
+                "Run diverged from the recorded host calls: {}",
+                divergence
+            ));
+        }
+        if state.next < self.calls.len() {
+            return Err(new_error!(
+                "Run diverged from the recorded host calls: made {} of the {} recorded calls",
+                state.next,
+                self.calls.len()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(function: &str, args: Vec<Value>, response: Value) -> HostCall {
+        HostCall {
+            function: function.to_string(),
+            args,
+            response,
+        }
+    }
+
+    fn unreachable() -> Result<Value> {
+        panic!("host function called in replay mode")
+    }
+
+    #[test]
+    fn host_call_json_round_trip() {
+        let recorded = call("HostPrint", vec![json!("hi\n")], json!(3));
+
+        assert_eq!(HostCall::from_json(&recorded.to_json()).unwrap(), recorded);
+    }
+
+    #[test]
+    fn host_call_from_json_requires_function_and_args() {
+        let call = HostCall::from_json(&json!({"function": "HostTimeNs", "args": []})).unwrap();
+        assert_eq!(call.response, Value::Null);
+
+        assert!(HostCall::from_json(&json!({"args": [], "response": 1})).is_err());
+        assert!(HostCall::from_json(&json!({"function": 1, "args": []})).is_err());
+        assert!(HostCall::from_json(&json!({"function": "HostTimeNs"})).is_err());
+        assert!(HostCall::from_json(&json!("HostTimeNs")).is_err());
+    }
+
+    #[test]
+    fn replay_answers_the_recorded_calls_in_order() {
+        let replay = HostCallReplay::new(vec![
+            call("HostTimeNs", vec![], json!(1)),
+            call("HostSleepNs", vec![json!(5)], Value::Null),
+            call("HostTimeNs", vec![], json!(6)),
+        ]);
+        let host_calls = HostCalls::new(true, None, Some(replay));
+
+        host_calls.begin_run();
+        assert_eq!(
+            host_calls.call("HostTimeNs", vec![], unreachable).unwrap(),
+            json!(1)
+        );
+        assert_eq!(
+            host_calls
+                .call("HostSleepNs", vec![json!(5)], unreachable)
+                .unwrap(),
+            Value::Null
+        );
+        assert_eq!(
+            host_calls.call("HostTimeNs", vec![], unreachable).unwrap(),
+            json!(6)
+        );
+        host_calls.end_run().unwrap();
+        assert_eq!(host_calls.calls().len(), 3);
+
+        // Every run replays the recording from the start
+        host_calls.begin_run();
+        assert_eq!(
+            host_calls.call("HostTimeNs", vec![], unreachable).unwrap(),
+            json!(1)
+        );
+        assert_eq!(host_calls.calls().len(), 1);
+    }
+
+    #[test]
+    fn replay_reports_the_first_divergence() {
+        let replay = HostCallReplay::new(vec![
+            call("HostSleepNs", vec![json!(5)], Value::Null),
+            call("HostTimeNs", vec![], json!(6)),
+        ]);
+        let host_calls = HostCalls::new(false, None, Some(replay));
+
+        host_calls.begin_run();
+        let err = host_calls
+            .call("HostSleepNs", vec![json!(7)], unreachable)
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("host call #1 HostSleepNs([7])"),
+            "{}",
+            err
+        );
+
+        // Later calls fail with the same divergence, even if they match the recording
+        let later = host_calls
+            .call("HostTimeNs", vec![], unreachable)
+            .unwrap_err();
+        assert_eq!(later.to_string(), err.to_string());
+
+        let err = host_calls.end_run().unwrap_err();
+        assert!(err.to_string().contains("diverged"), "{}", err);
+    }
+
+    #[test]
+    fn replay_requires_every_recorded_call() {
+        let replay = HostCallReplay::new(vec![call("HostTimeNs", vec![], json!(1))]);
+        let host_calls = HostCalls::new(false, None, Some(replay));
+
+        host_calls.begin_run();
+        let err = host_calls.end_run().unwrap_err();
+        assert!(
+            err.to_string().contains("made 0 of the 1 recorded calls"),
+            "{}",
+            err
+        );
+
+        host_calls.begin_run();
+        host_calls.call("HostTimeNs", vec![], unreachable).unwrap();
+        let err = host_calls
+            .call("HostTimeNs", vec![], unreachable)
+            .unwrap_err();
+        assert!(err.to_string().contains("end of the run"), "{}", err);
+    }
+
+    #[test]
+    fn recording_saved_by_a_run_can_be_replayed() {
+        let path = std::env::temp_dir().join(format!(
+            "hyperlight-python-host-calls-{}.jsonl",
+            std::process::id()
+        ));
+        let host_calls = HostCalls::new(false, Some(path.clone()), None);
+
+        host_calls.begin_run();
+        host_calls
+            .call("HostPrint", vec![json!("hi")], || Ok(json!(2)))
+            .unwrap();
+        host_calls
+            .call("HostRandomSeed", vec![], || Ok(json!(42)))
+            .unwrap();
+        host_calls.end_run().unwrap();
+
+        let replay = HostCallReplay::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(*replay.unwrap().calls, host_calls.calls());
+    }
+
+    #[test]
+    fn calls_are_not_recorded_unless_enabled() {
+        let host_calls = HostCalls::new(false, None, None);
+
+        host_calls.begin_run();
+        assert_eq!(
+            host_calls
+                .call("HostTimeNs", vec![], || Ok(json!(1)))
+                .unwrap(),
+            json!(1)
+        );
+        assert!(host_calls.calls().is_empty());
+        host_calls.end_run().unwrap();
+    }
+}
@@ -1,6 +1,19 @@
-use hyperlight_host::{MultiUseSandbox, Result, sandbox::snapshot::Snapshot};
+#[cfg(feature = "tokio")]
+use std::sync::Arc;
+use std::time::Instant;
 
-use crate::sandbox::PySandbox;
+#[cfg(feature = "tokio")]
+use hyperlight_host::hypervisor::InterruptHandle;
+use hyperlight_host::{MultiUseSandbox, Result, new_error, sandbox::snapshot::Snapshot};
+
+#[cfg(feature = "tokio")]
+use crate::sandbox::clock::Clock;
+use crate::sandbox::runtime_config::RuntimeConfig;
+use crate::sandbox::sandbox_factory::{DETERMINISTIC_SEED, SandboxFactory};
+use crate::sandbox::{
+    ExecOptions, ExecStats, HostCall, PyHandler, PySandbox, PyValue, RecoveryEvent, RecoveryPolicy,
+    TransitionError,
+};
 
 /// Loaded Python sandbox for executing Python code.
 /// This sandbox has the Python runtime loaded and initialized and it allows
@@ -10,6 +23,14 @@ pub struct LoadedPySandbox {
     inner: MultiUseSandbox,
     /// Snapshot of the initial state before loading the Python runtime
     snapshot: Snapshot,
+    /// Snapshot of the state right after the Python runtime was loaded
+    loaded_snapshot: Snapshot,
+    /// Error of the run that poisoned the sandbox, if any
+    poison_reason: Option<String>,
+    /// Factory the inner sandbox was created with
+    factory: SandboxFactory,
+    /// Settings applied once the Python runtime is initialized
+    config: RuntimeConfig,
 }
 
 impl LoadedPySandbox {
@@ -17,23 +38,54 @@ impl LoadedPySandbox {
     /// # Arguments
     /// * `inner` - The inner multi-use sandbox with the Python runtime loaded
     /// * `snapshot` - The snapshot of the initial state before loading the Python runtime
-    pub(super) fn new(inner: MultiUseSandbox, snapshot: Snapshot) -> Result<LoadedPySandbox> {
-        Ok(LoadedPySandbox { inner, snapshot })
+    /// * `loaded_snapshot` - The snapshot of the state right after the Python runtime was loaded
+    /// * `factory` - The factory the inner sandbox was created with
+    /// * `config` - Settings applied once the Python runtime is initialized
+    pub(super) fn new(
+        inner: MultiUseSandbox,
+        snapshot: Snapshot,
+        loaded_snapshot: Snapshot,
+        factory: SandboxFactory,
+        config: RuntimeConfig,
+    ) -> LoadedPySandbox {
+        LoadedPySandbox {
+            inner,
+            snapshot,
+            loaded_snapshot,
+            poison_reason: None,
+            factory,
+            config,
+        }
     }
 
     /// Returns whether the sandbox is poisoned.
     /// A poisoned sandbox indicates that a previous operation has failed
     /// and the sandbox is no longer in a valid state for further operations.
+    /// Unless the [`RecoveryPolicy`] is [`RecoveryPolicy::Never`], the sandbox
+    /// recovers before the next script is run.
     pub fn poisoned(&self) -> bool {
         self.inner.poisoned()
     }
 
+    /// Handle for cancelling the script currently running in the sandbox.
+    /// A cancelled run poisons the sandbox, see [`RecoveryPolicy`].
+    #[cfg(feature = "tokio")]
+    pub(crate) fn interrupt_handle(&self) -> Arc<dyn InterruptHandle> {
+        self.inner.interrupt_handle()
+    }
+
+    /// Clock read by the `time` module, interrupted along with the running script
+    #[cfg(feature = "tokio")]
+    pub(crate) fn clock(&self) -> Clock {
+        self.factory.clock().clone()
+    }
+
     /// Run a Python script in the sandbox.
     /// # Arguments
     /// * `code` - The Python code to execute as a string
     /// # Returns
-    /// * `Result<bool>` - Returns `Ok(true)` if the script executed successfully, otherwise
-    /// returns an error.
+    /// * `Result<bool>` - Returns `Ok(true)` if the script executed successfully, `Ok(false)`
+    /// if it raised an exception, otherwise returns an error.
     ///
     /// # Example
     /// ```
@@ -60,7 +112,467 @@ impl LoadedPySandbox {
     /// }
     /// ```
     pub fn run_script(&mut self, code: String) -> Result<bool> {
-        self.inner.call("exec_python", code)
+        self.recover()?;
+        self.begin_script_run()?;
+
+        let result = self.inner.call("exec_python", code);
+        let result = self.track_poison(result);
+        self.end_run(result)
+    }
+
+    /// Run a Python script in the sandbox and report the resources it used.
+    /// # Arguments
+    /// * `code` - The Python code to execute as a string
+    /// # Returns
+    /// * `Result<(bool, ExecStats)>` - Returns `Ok(true)` together with the [`ExecStats`]
+    /// of the run if the script executed successfully, `Ok(false)` if it raised an exception,
+    /// otherwise returns an error.
+    ///
+    /// # Example
+    /// ```
+    /// use hyperlight_python::sandbox::SandboxBuilder;
+    ///
+    /// fn main() -> hyperlight_host::Result<()> {
+    ///     let proto_sbox = SandboxBuilder::new()
+    ///         .build()?;
+    ///
+    ///     let sandbox = proto_sbox.load_runtime()?;
+    ///     let mut sandbox = sandbox.get_loaded_sandbox()?;
+    ///
+    ///     let code = "print([i * i for i in range(100)])".to_string();
+    ///     let (success, stats) = sandbox.run_script_with_stats(code)?;
+    ///     assert!(success);
+    ///     println!("{} bytes printed in {:?}", stats.bytes_printed, stats.wall_time);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn run_script_with_stats(&mut self, code: String) -> Result<(bool, ExecStats)> {
+        self.run_script_with_options(code, &ExecOptions::default())
+    }
+
+    /// Run a Python script in the sandbox with the given [`ExecOptions`]
+    /// and report the resources it used.
+    /// # Arguments
+    /// * `code` - The Python code to execute as a string
+    /// * `options` - The options for this run
+    /// # Returns
+    /// * `Result<(bool, ExecStats)>` - Returns `Ok(true)` together with the [`ExecStats`]
+    /// of the run if the script executed successfully, `Ok(false)` if it raised an exception
+    /// (including the `SystemExit` raised by [`OutputLimitPolicy::TruncateAndAbort`]),
+    /// otherwise returns an error.
+    ///
+    /// [`OutputLimitPolicy::TruncateAndAbort`]: crate::sandbox::OutputLimitPolicy::TruncateAndAbort
+    ///
+    /// # Example
+    /// ```
+    /// use hyperlight_python::sandbox::{ExecOptions, OutputLimitPolicy, SandboxBuilder};
+    ///
+    /// fn main() -> hyperlight_host::Result<()> {
+    ///     let proto_sbox = SandboxBuilder::new()
+    ///         .build()?;
+    ///
+    ///     let sandbox = proto_sbox.load_runtime()?;
+    ///     let mut sandbox = sandbox.get_loaded_sandbox()?;
+    ///
+    ///     let options = ExecOptions::new()
+    ///         .max_output_bytes(16)
+    ///         .output_limit_policy(OutputLimitPolicy::TruncateAndContinue);
+    ///
+    ///     let code = "print('x' * 1000)".to_string();
+    ///     let (success, stats) = sandbox.run_script_with_options(code, &options)?;
+    ///     assert!(success);
+    ///     assert!(stats.output_truncated);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn run_script_with_options(
+        &mut self,
+        code: String,
+        options: &ExecOptions,
+    ) -> Result<(bool, ExecStats)> {
+        options.validate()?;
+        self.recover()?;
+        self.begin_script_run()?;
+        options.apply(&mut self.inner)?;
+
+        let start = Instant::now();
+        let result = self.inner.call("exec_python", code);
+        // Stopped before end_run, which may write the host call recording
+        let wall_time = start.elapsed();
+        let result = self.track_poison(result);
+        let success = self.end_run(result)?;
+
+        let counters = self.inner.call::<Vec<u8>>("exec_stats", ())?;
+        let stats =
+            ExecStats::from_guest(&counters, wall_time, self.factory.host_timer().elapsed())?;
+
+        if stats.output_truncated && options.fails_on_output_limit() {
+            return Err(new_error!(
+                "Script output exceeded the limit of {} bytes",
+                options.output_limit().unwrap_or_default()
+            ));
+        }
+
+        Ok((success, stats))
+    }
+
+    /// Host calls made by the last run, i.e. the last script run, expression or function
+    /// call, with their arguments and responses.
+    /// Only recorded by sandboxes built with
+    /// [`SandboxBuilder::deterministic`](crate::sandbox::SandboxBuilder::deterministic),
+    /// [`SandboxBuilder::record_host_calls`](crate::sandbox::SandboxBuilder::record_host_calls) or
+    /// [`SandboxBuilder::replay_host_calls`](crate::sandbox::SandboxBuilder::replay_host_calls),
+    /// empty otherwise.
+    pub fn host_calls(&self) -> Vec<HostCall> {
+        self.factory.host_calls().calls()
+    }
+
+    /// Text of the exception raised by the last script run, if any.
+    /// The text holds the traceback and is truncated to 1 kB.
+    /// # Returns
+    /// * `Result<Option<String>>` - Returns `Ok(None)` if the last script ran to completion.
+    ///
+    /// # Example
+    /// ```
+    /// use hyperlight_python::sandbox::SandboxBuilder;
+    ///
+    /// fn main() -> hyperlight_host::Result<()> {
+    ///     let proto_sbox = SandboxBuilder::new()
+    ///         .build()?;
+    ///
+    ///     let sandbox = proto_sbox.load_runtime()?;
+    ///     let mut sandbox = sandbox.get_loaded_sandbox()?;
+    ///
+    ///     let success = sandbox.run_script("1 / 0".to_string())?;
+    ///     assert!(!success);
+    ///     let exception = sandbox.last_exception()?.unwrap_or_default();
+    ///     assert!(exception.contains("ZeroDivisionError"));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn last_exception(&mut self) -> Result<Option<String>> {
+        let text = self.inner.call::<String>("last_exception", ())?;
+
+        Ok(Some(text).filter(|text| !text.is_empty()))
+    }
+
+    /// Evaluate a Python expression in the sandbox and return its value.
+    /// Names defined by previous scripts are visible to the expression.
+    /// # Arguments
+    /// * `code` - The Python expression to evaluate
+    /// # Returns
+    /// * `Result<PyValue>` - The value of the expression. Returns an error if it raised
+    /// an exception or its value can't be converted to a [`PyValue`].
+    ///
+    /// # Example
+    /// ```
+    /// use hyperlight_python::sandbox::{PyValue, SandboxBuilder};
+    ///
+    /// fn main() -> hyperlight_host::Result<()> {
+    ///     let proto_sbox = SandboxBuilder::new()
+    ///         .build()?;
+    ///
+    ///     let sandbox = proto_sbox.load_runtime()?;
+    ///     let mut sandbox = sandbox.get_loaded_sandbox()?;
+    ///
+    ///     let value = sandbox.eval("[i * i for i in range(4)]")?;
+    ///     let squares = [0, 1, 4, 9].into_iter().map(PyValue::Int).collect();
+    ///     assert_eq!(value, PyValue::List(squares));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn eval(&mut self, code: &str) -> Result<PyValue> {
+        self.recover()?;
+        self.begin_run()?;
+
+        let result = self.inner.call::<Vec<u8>>("eval_python", code.to_string());
+        let result = self.track_poison(result);
+        PyValue::from_response(&self.end_run(result)?)
+    }
+
+    /// Call a function defined by a previous script and return its value.
+    /// # Arguments
+    /// * `name` - Name of the function
+    /// * `args` - The positional arguments of the call
+    /// # Returns
+    /// * `Result<PyValue>` - The return value of the function. Returns an error if the
+    /// function isn't defined, it raised an exception or its return value can't be
+    /// converted to a [`PyValue`].
+    ///
+    /// # Example
+    /// ```
+    /// use hyperlight_python::sandbox::{PyValue, SandboxBuilder};
+    ///
+    /// fn main() -> hyperlight_host::Result<()> {
+    ///     let proto_sbox = SandboxBuilder::new()
+    ///         .build()?;
+    ///
+    ///     let sandbox = proto_sbox.load_runtime()?;
+    ///     let mut sandbox = sandbox.get_loaded_sandbox()?;
+    ///
+    ///     sandbox.run_script("def greet(name):\n    return 'Hello, ' + name".to_string())?;
+    ///     let value = sandbox.call_function("greet", &["World".into()])?;
+    ///     assert_eq!(value, PyValue::Str("Hello, World".to_string()));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn call_function(&mut self, name: &str, args: &[PyValue]) -> Result<PyValue> {
+        self.recover()?;
+
+        let args = PyValue::Tuple(args.to_vec()).encode();
+        self.begin_run()?;
+
+        let result = self
+            .inner
+            .call::<Vec<u8>>("call_python", (name.to_string(), args));
+        let result = self.track_poison(result);
+        PyValue::from_response(&self.end_run(result)?)
+    }
+
+    /// Decode a JSON document with `json.loads` in the sandbox and store it as a global,
+    /// visible to the scripts and expressions run afterwards.
+    /// # Arguments
+    /// * `name` - Name of the global
+    /// * `value` - The JSON document
+    /// # Returns
+    /// * `Result<()>` - Returns an error if the document couldn't be decoded in the sandbox.
+    ///
+    /// # Example
+    /// ```
+    /// use hyperlight_python::sandbox::SandboxBuilder;
+    /// use serde_json::json;
+    ///
+    /// fn main() -> hyperlight_host::Result<()> {
+    ///     let proto_sbox = SandboxBuilder::new()
+    ///         .build()?;
+    ///
+    ///     let sandbox = proto_sbox.load_runtime()?;
+    ///     let mut sandbox = sandbox.get_loaded_sandbox()?;
+    ///
+    ///     sandbox.set_global_json("order", &json!({"items": [3, 4], "discount": 0.5}))?;
+    ///     let total = sandbox.eval_json("{'total': sum(order['items']) * order['discount']}")?;
+    ///     assert_eq!(total, json!({"total": 3.5}));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn set_global_json(&mut self, name: &str, value: &serde_json::Value) -> Result<()> {
+        self.recover()?;
+
+        let json = value.to_string();
+        let result = self
+            .inner
+            .call::<Vec<u8>>("set_global_json", (name.to_string(), json));
+        PyValue::from_response(&self.track_poison(result)?)?;
+
+        Ok(())
+    }
+
+    /// Evaluate a Python expression in the sandbox and return its value encoded
+    /// with `json.dumps`. Names defined by previous scripts are visible to the expression.
+    /// See [`LoadedPySandbox::set_global_json`] for an example.
+    /// # Arguments
+    /// * `code` - The Python expression to evaluate
+    /// # Returns
+    /// * `Result<serde_json::Value>` - The value of the expression. Returns an error if it
+    /// raised an exception or `json.dumps` couldn't encode it.
+    pub fn eval_json(&mut self, code: &str) -> Result<serde_json::Value> {
+        self.recover()?;
+        self.begin_run()?;
+
+        let result = self.inner.call::<Vec<u8>>("eval_json", code.to_string());
+        let result = self.track_poison(result);
+        match PyValue::from_response(&self.end_run(result)?)? {
+            PyValue::Str(json) => serde_json::from_str(&json)
+                .map_err(|e| new_error!("Invalid JSON from guest: {}", e)),
+            value => Err(new_error!("Invalid JSON from guest: {:?}", value)),
+        }
+    }
+
+    /// Define a handler from its source and obtain a [`PyHandler`] invoking it.
+    /// The source must define a `handle(event)` function; the state right after
+    /// it ran is restored before every invocation.
+    /// # Arguments
+    /// * `source` - The Python source defining the handler
+    /// # Errors
+    /// Returns a [`TransitionError`] carrying this sandbox back if the source raised
+    /// an exception or doesn't define a `handle` function.
+    ///
+    /// # Example
+    /// ```
+    /// use hyperlight_python::sandbox::{PyValue, SandboxBuilder};
+    ///
+    /// fn main() -> hyperlight_host::Result<()> {
+    ///     let proto_sbox = SandboxBuilder::new()
+    ///         .build()?;
+    ///
+    ///     let sandbox = proto_sbox.load_runtime()?;
+    ///     let sandbox = sandbox.get_loaded_sandbox()?;
+    ///
+    ///     let source = r#"
+    /// seen = []
+    /// def handle(event):
+    ///     seen.append(event)
+    ///     return len(seen)
+    /// "#;
+    ///     let mut handler = sandbox.register_handler(source.to_string())?;
+    ///
+    ///     // State never leaks between invocations
+    ///     assert_eq!(handler.invoke("first".into())?, PyValue::Int(1));
+    ///     assert_eq!(handler.invoke("second".into())?, PyValue::Int(1));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn register_handler(
+        mut self,
+        source: String,
+    ) -> std::result::Result<PyHandler, TransitionError<Self>> {
+        let snapshot = match self.define_handler(source) {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                // Drop whatever the source defined so the sandbox can be reused
+                let _ = self.restore_loaded();
+                return Err(TransitionError::new(self, e));
+            }
+        };
+
+        Ok(PyHandler::new(self, snapshot))
+    }
+
+    /// Run the handler source and take a snapshot of the state right after it
+    fn define_handler(&mut self, source: String) -> Result<Snapshot> {
+        if !self.run_script(source)? {
+            return Err(new_error!("Handler source raised an exception"));
+        }
+
+        // A NameError means there is no handle function either
+        if !matches!(self.eval("callable(handle)"), Ok(PyValue::Bool(true))) {
+            return Err(new_error!(
+                "Handler source doesn't define a handle function"
+            ));
+        }
+
+        self.inner.snapshot()
+    }
+
+    /// Restore the sandbox to the state right after the Python runtime was loaded
+    pub(super) fn restore_loaded(&mut self) -> Result<()> {
+        self.inner.restore(&self.loaded_snapshot)?;
+        self.poison_reason = None;
+
+        Ok(())
+    }
+
+    /// Restore the sandbox to a snapshot taken from it, which also clears its poisoned state
+    /// # Arguments
+    /// * `snapshot` - The snapshot to restore
+    pub(super) fn restore(&mut self, snapshot: &Snapshot) -> Result<()> {
+        self.inner.restore(snapshot)?;
+        self.poison_reason = None;
+
+        Ok(())
+    }
+
+    /// Begin a script run, restoring the state right after the Python runtime was
+    /// loaded first if the sandbox was built with
+    /// [`SandboxBuilder::restore_each_run`](crate::sandbox::SandboxBuilder::restore_each_run)
+    fn begin_script_run(&mut self) -> Result<()> {
+        if self.config.restore_each_run {
+            self.restore_loaded()?;
+        }
+
+        self.begin_run()
+    }
+
+    /// Reset the per-run state of the host functions before running guest code:
+    /// a script, or an expression or function call made with [`LoadedPySandbox::eval`],
+    /// [`LoadedPySandbox::eval_json`] or [`LoadedPySandbox::call_function`].
+    /// In deterministic mode, also rewind the clock and reseed the `random` module, which
+    /// would otherwise carry on from the previous run. Seeding comes first so that the
+    /// host call the module makes to seed itself on its first import isn't part of the run.
+    fn begin_run(&mut self) -> Result<()> {
+        if self.config.deterministic {
+            if !self.inner.call::<bool>("seed_random", DETERMINISTIC_SEED)? {
+                return Err(new_error!("Could not seed the random module"));
+            }
+            self.factory.clock().reset();
+        }
+
+        self.factory.host_timer().reset();
+        self.factory.host_calls().begin_run();
+
+        Ok(())
+    }
+
+    /// Write the host calls of the run that just ended if they are recorded to a file,
+    /// and check them against the recording in replay mode.
+    /// The error of the run itself takes precedence.
+    /// # Arguments
+    /// * `result` - The result of the run
+    fn end_run<T>(&self, result: Result<T>) -> Result<T> {
+        let ended = self.factory.host_calls().end_run();
+        let value = result?;
+        ended?;
+
+        Ok(value)
+    }
+
+    /// Remember why the sandbox got poisoned if a call failed and poisoned it
+    /// # Arguments
+    /// * `result` - The result of the call
+    fn track_poison<T>(&mut self, result: Result<T>) -> Result<T> {
+        if let Err(e) = &result
+            && self.inner.poisoned()
+        {
+            self.poison_reason = Some(e.to_string());
+        }
+
+        result
+    }
+
+    /// Recover a poisoned sandbox according to its [`RecoveryPolicy`]
+    /// and notify the recovery handler.
+    /// Does nothing if the sandbox is not poisoned or the policy is [`RecoveryPolicy::Never`].
+    fn recover(&mut self) -> Result<()> {
+        let policy = self.config.recovery_policy;
+        if !self.inner.poisoned() || policy == RecoveryPolicy::Never {
+            return Ok(());
+        }
+
+        match policy {
+            RecoveryPolicy::Never => {}
+            RecoveryPolicy::RestoreLastSnapshot => {
+                self.inner
+                    .restore(&self.loaded_snapshot)
+                    .map_err(|e| new_error!("Could not restore poisoned sandbox: {:?}", e))?;
+            }
+            RecoveryPolicy::RebuildFromScratch => {
+                let mut inner = self
+                    .factory
+                    .create()
+                    .and_then(|usbox| usbox.evolve())
+                    .map_err(|e| new_error!("Could not rebuild poisoned sandbox: {:?}", e))?;
+                let snapshot = inner.snapshot()?;
+                PySandbox::initialize(&mut inner, &self.config)?;
+                let loaded_snapshot = inner.snapshot()?;
+
+                self.inner = inner;
+                self.snapshot = snapshot;
+                self.loaded_snapshot = loaded_snapshot;
+            }
+        }
+
+        let event = RecoveryEvent {
+            policy,
+            reason: self
+                .poison_reason
+                .take()
+                .unwrap_or_else(|| "unknown error".to_string()),
+        };
+        if let Some(handler) = &self.config.recovery_handler {
+            handler.notify(&event);
+        }
+
+        Ok(())
     }
 
     /// Unload the Python runtime and return to a [`PySandbox`].
@@ -68,7 +580,11 @@ impl LoadedPySandbox {
     /// and it cannot run Python scripts until it is loaded again.
     ///
     /// # Returns
-    /// * `Result<PySandbox>` - The unloaded Python sandbox.
+    /// * `Result<PySandbox, TransitionError<LoadedPySandbox>>` - The unloaded Python sandbox.
+    ///
+    /// # Errors
+    /// Returns a [`TransitionError`] carrying this sandbox back if it could not be
+    /// restored to its state before the Python runtime was loaded.
     ///
     /// # Example
     /// ```
@@ -85,7 +601,16 @@ impl LoadedPySandbox {
     ///     Ok(())
     /// }
     /// ```
-    pub fn unload(self) -> Result<PySandbox> {
-        PySandbox::from_loaded(self.inner, self.snapshot)
+    pub fn unload(mut self) -> std::result::Result<PySandbox, TransitionError<Self>> {
+        if let Err(e) = self.inner.restore(&self.snapshot) {
+            return Err(TransitionError::new(self, e));
+        }
+
+        Ok(PySandbox::new(
+            self.inner,
+            self.snapshot,
+            self.factory,
+            self.config,
+        ))
     }
 }
@@ -1,13 +1,43 @@
+#[cfg(feature = "tokio")]
+mod async_loaded_py_sandbox;
+mod batch;
+mod builtin_policy;
+mod clock;
+mod exec_options;
+mod exec_stats;
+mod guest_info;
+mod host_calls;
 mod loaded_py_sandbox;
+mod module_policy;
 mod proto_py_sandbox;
+mod py_handler;
 mod py_sandbox;
+mod py_value;
+mod recovery;
+mod runtime_config;
 mod sandbox_builder;
+mod sandbox_factory;
+mod transition_error;
 
+#[cfg(feature = "tokio")]
+pub use async_loaded_py_sandbox::{AsyncLoadedPySandbox, OutputStream};
+pub use batch::BatchResult;
+pub use builtin_policy::BuiltinPolicy;
+pub use clock::ClockPolicy;
+pub use exec_options::{ExecOptions, OutputLimitPolicy};
+pub use exec_stats::ExecStats;
+pub use guest_info::GuestInfo;
+pub use host_calls::HostCall;
 pub use loaded_py_sandbox::LoadedPySandbox;
 pub use proto_py_sandbox::ProtoPySandbox;
+pub use py_handler::PyHandler;
 pub use py_sandbox::PySandbox;
+pub use py_value::PyValue;
+pub use recovery::{RecoveryEvent, RecoveryPolicy};
 pub use sandbox_builder::SandboxBuilder;
+pub use transition_error::TransitionError;
 
 // This include! macro is replaced by the build.rs script.
-// The build.rs script reads the jshost.exe binary into a static byte array named PYTHONHOST.
+// The build.rs script reads the python-host binary into a static byte array named PYHOST,
+// which is None with the custom-guest feature.
 include!(concat!(env!("OUT_DIR"), "/host_resource.rs"));
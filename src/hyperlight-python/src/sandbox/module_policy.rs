@@ -0,0 +1,180 @@
+use hyperlight_host::{MultiUseSandbox, Result, new_error};
+
+/// Policy mode understood by the guest `set_module_policy` function for an allowlist
+const MODE_ALLOW: i32 = 1;
+/// Policy mode understood by the guest `set_module_policy` function for a denylist
+const MODE_DENY: i32 = 2;
+
+/// Modules that scripts in the sandbox are allowed to import.
+/// The policy is enforced by the guest import hook, so it applies to built-in,
+/// frozen and host-registered modules alike. Listing a package also covers its submodules.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) enum ModulePolicy {
+    /// Every module can be imported
+    #[default]
+    Unrestricted,
+    /// Only the listed modules can be imported
+    Allow(Vec<String>),
+    /// Every module except the listed ones can be imported
+    Deny(Vec<String>),
+}
+
+impl ModulePolicy {
+    /// Send the policy to the guest.
+    /// Must be called after the Python runtime is initialized.
+    /// # Arguments
+    /// * `sandbox` - The sandbox with the Python runtime initialized
+    pub(crate) fn apply(&self, sandbox: &mut MultiUseSandbox) -> Result<()> {
+        let (mode, modules) = match self {
+            ModulePolicy::Unrestricted => return Ok(()),
+            ModulePolicy::Allow(modules) => (MODE_ALLOW, modules.join("\n")),
+            ModulePolicy::Deny(modules) => (MODE_DENY, modules.join("\n")),
+        };
+
+        let applied = sandbox.call::<bool>("set_module_policy", (mode, modules))?;
+        if !applied {
+            return Err(new_error!("Guest rejected the module policy {:?}", self));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sandbox::{PyValue, SandboxBuilder};
+
+    /// Message of the `ImportError` raised by `import <module>`, `None` if it succeeded
+    fn import_error(builder: SandboxBuilder, module: &str) -> Option<String> {
+        let sandbox = builder.build().unwrap().load_runtime().unwrap();
+        let mut sandbox = sandbox.get_loaded_sandbox().unwrap();
+        let code = format!(
+            "try:\n    import {module}\n    error = None\nexcept ImportError as e:\n    error = str(e)"
+        );
+        assert!(sandbox.run_script(code).unwrap());
+
+        match sandbox.eval("error").unwrap() {
+            PyValue::None => None,
+            PyValue::Str(error) => Some(error),
+            error => panic!("unexpected error {:?}", error),
+        }
+    }
+
+    fn blocked(module: &str) -> Option<String> {
+        Some(format!(
+            "import of '{module}' is blocked by the sandbox module policy"
+        ))
+    }
+
+    #[test]
+    fn allowlist_blocks_unlisted_modules() {
+        let builder = SandboxBuilder::new().allow_modules(["json"]);
+
+        assert_eq!(import_error(builder.clone(), "json"), None);
+        assert_eq!(import_error(builder, "gc"), blocked("gc"));
+    }
+
+    #[test]
+    fn denylist_blocks_listed_modules_and_their_submodules() {
+        let builder = SandboxBuilder::new().deny_modules(["json"]);
+
+        assert_eq!(import_error(builder.clone(), "json"), blocked("json"));
+        assert_eq!(
+            import_error(builder.clone(), "json.decoder"),
+            blocked("json.decoder")
+        );
+        assert_eq!(import_error(builder, "gc"), None);
+    }
+
+    #[test]
+    fn module_names_match_whole_components() {
+        // `js` is not a package containing `json`
+        let builder = SandboxBuilder::new().deny_modules(["js"]);
+        assert_eq!(import_error(builder, "json"), None);
+
+        let builder = SandboxBuilder::new().allow_modules(["js"]);
+        assert_eq!(import_error(builder, "json"), blocked("json"));
+    }
+
+    #[test]
+    fn unrestricted_policy_allows_every_module() {
+        assert_eq!(import_error(SandboxBuilder::new(), "gc"), None);
+        assert_eq!(import_error(SandboxBuilder::new(), "array"), None);
+    }
+
+    #[test]
+    fn native_modules_are_imported_and_called() {
+        let sandbox = SandboxBuilder::new()
+            .build()
+            .unwrap()
+            .load_runtime()
+            .unwrap();
+        let mut sandbox = sandbox.get_loaded_sandbox().unwrap();
+
+        assert!(sandbox.run_script("import fastmath".to_string()).unwrap());
+        assert_eq!(
+            sandbox.eval("fastmath.clamp(42, 0, 10)").unwrap(),
+            PyValue::Int(10)
+        );
+        assert_eq!(
+            sandbox.eval("fastmath.clamp(-3, 0, 10)").unwrap(),
+            PyValue::Int(0)
+        );
+
+        let code = "try:\n    fastmath.clamp(1, 10, 0)\n    error = None\nexcept ValueError as e:\n    error = str(e)";
+        assert!(sandbox.run_script(code.to_string()).unwrap());
+        assert_eq!(
+            sandbox.eval("error").unwrap(),
+            PyValue::Str("low is greater than high".to_string())
+        );
+    }
+
+    #[test]
+    fn native_modules_follow_the_policy() {
+        let builder = SandboxBuilder::new().deny_modules(["fastmath"]);
+        assert_eq!(import_error(builder, "fastmath"), blocked("fastmath"));
+
+        let builder = SandboxBuilder::new().allow_modules(["fastmath"]);
+        assert_eq!(import_error(builder, "fastmath"), None);
+    }
+
+    #[test]
+    fn native_modules_convert_lists_and_dicts() {
+        let sandbox = SandboxBuilder::new()
+            .build()
+            .unwrap()
+            .load_runtime()
+            .unwrap();
+        let mut sandbox = sandbox.get_loaded_sandbox().unwrap();
+        assert!(sandbox.run_script("import fastmath".to_string()).unwrap());
+
+        assert_eq!(
+            sandbox.eval_json("fastmath.cumsum([1, 2, 3, 4])").unwrap(),
+            serde_json::json!([1, 3, 6, 10])
+        );
+        assert_eq!(
+            sandbox.eval_json("fastmath.cumsum(())").unwrap(),
+            serde_json::json!([])
+        );
+        assert_eq!(
+            sandbox
+                .eval_json("sorted(fastmath.histogram([3, 1, 3, 3]).items())")
+                .unwrap(),
+            serde_json::json!([[1, 1], [3, 3]])
+        );
+        assert_eq!(
+            sandbox
+                .eval_json("fastmath.scale({'a': 1.5, 'b': -2.0}, 2)")
+                .unwrap(),
+            serde_json::json!({"a": 3.0, "b": -4.0})
+        );
+
+        // Items of the wrong type raise into the script
+        let code = "try:\n    fastmath.cumsum([1, 'two'])\n    error = None\nexcept TypeError:\n    error = 'TypeError'";
+        assert!(sandbox.run_script(code.to_string()).unwrap());
+        assert_eq!(
+            sandbox.eval("error").unwrap(),
+            PyValue::Str("TypeError".to_string())
+        );
+    }
+}
@@ -1,5 +1,7 @@
-use crate::{HostPrintFn, sandbox::PySandbox};
-use hyperlight_host::{GuestBinary, Result, UninitializedSandbox, sandbox::SandboxConfiguration};
+use crate::sandbox::runtime_config::RuntimeConfig;
+use crate::sandbox::sandbox_factory::SandboxFactory;
+use crate::sandbox::{PySandbox, TransitionError};
+use hyperlight_host::{HyperlightError, Result, UninitializedSandbox};
 
 /// Sandbox for initializing a Python runtime.
 /// This sandbox does not have the Python runtime loaded yet.
@@ -24,41 +26,77 @@ use hyperlight_host::{GuestBinary, Result, UninitializedSandbox, sandbox::Sandbo
 /// }
 /// ```
 pub struct ProtoPySandbox {
-    /// Inner uninitialized sandbox
-    inner: UninitializedSandbox,
+    /// Inner uninitialized sandbox.
+    /// `None` after a failed [`ProtoPySandbox::load_runtime`] if a new one could not be created.
+    inner: Option<UninitializedSandbox>,
+    /// Factory the inner sandbox was created with
+    factory: SandboxFactory,
+    /// Settings applied once the Python runtime is initialized
+    config: RuntimeConfig,
 }
 
 impl ProtoPySandbox {
     /// Create a new [`ProtoPySandbox`]
     ///
     /// # Arguments
-    /// * `guest_binary` - The guest binary to use for the sandbox
-    /// * `cfg` - Optional configuration for the sandbox
-    /// * `host_print_writer` - Optional host print function
+    /// * `factory` - The factory creating the inner sandbox
+    /// * `config` - Settings applied once the Python runtime is initialized
     ///
     /// # Errors
     /// Returns an error if the sandbox could not be created
-    pub(super) fn new(
-        guest_binary: GuestBinary,
-        cfg: Option<SandboxConfiguration>,
-        host_print_writer: Option<HostPrintFn>,
-    ) -> Result<Self> {
-        let mut usbox: UninitializedSandbox = UninitializedSandbox::new(guest_binary, cfg)?;
+    pub(super) fn new(factory: SandboxFactory, config: RuntimeConfig) -> Result<Self> {
+        let usbox: UninitializedSandbox = factory.create()?;
 
-        if let Some(host_print_writer) = host_print_writer {
-            usbox.register_print(host_print_writer)?;
-        }
-
-        Ok(Self { inner: usbox })
+        Ok(Self {
+            inner: Some(usbox),
+            factory,
+            config,
+        })
     }
 
     /// Load the Python runtime into the sandbox.
     /// This initializes the Python runtime and returns a [`PySandbox`].
     /// # Errors
-    /// Returns an error if the Python runtime could not be initialized.
-    pub fn load_runtime(self) -> Result<PySandbox> {
-        let multi_use_sandbox = self.inner.evolve()?;
+    /// Returns a [`TransitionError`] carrying this sandbox back if the Python runtime
+    /// could not be initialized. Since the failed attempt consumes the inner sandbox,
+    /// the returned sandbox holds a new one created with the same configuration, unless
+    /// creating it failed too, see [`TransitionError::rebuild_error`].
+    pub fn load_runtime(mut self) -> std::result::Result<PySandbox, TransitionError<Self>> {
+        let usbox = match self.inner.take().map_or_else(|| self.factory.create(), Ok) {
+            Ok(usbox) => usbox,
+            Err(e) => return Err(TransitionError::new(self, e)),
+        };
+
+        let mut multi_use_sandbox = match usbox.evolve() {
+            Ok(multi_use_sandbox) => multi_use_sandbox,
+            Err(e) => return Err(self.rebuild_after(e)),
+        };
+
+        match multi_use_sandbox.snapshot() {
+            Ok(snapshot) => Ok(PySandbox::new(
+                multi_use_sandbox,
+                snapshot,
+                self.factory,
+                self.config,
+            )),
+            Err(e) => Err(self.rebuild_after(e)),
+        }
+    }
 
-        PySandbox::new(multi_use_sandbox)
+    /// Create a new inner sandbox after a failed transition consumed it.
+    /// If that fails too, the error carries both errors back with a sandbox
+    /// without an inner sandbox, which creates one on the next attempt.
+    /// # Arguments
+    /// * `error` - The error that made the transition fail
+    fn rebuild_after(mut self, error: HyperlightError) -> TransitionError<Self> {
+        match self.factory.create() {
+            Ok(usbox) => {
+                self.inner = Some(usbox);
+                TransitionError::new(self, error)
+            }
+            Err(rebuild_error) => {
+                TransitionError::new(self, error).with_rebuild_error(rebuild_error)
+            }
+        }
     }
 }
@@ -0,0 +1,99 @@
+use hyperlight_host::sandbox::snapshot::Snapshot;
+use hyperlight_host::{Result, new_error};
+
+use crate::sandbox::{LoadedPySandbox, PyValue, TransitionError};
+
+/// Name of the function called by [`PyHandler::invoke`]
+const HANDLER_FUNCTION: &str = "handle";
+
+/// Handler defined by a Python source with a `handle(event)` function,
+/// obtained from [`LoadedPySandbox::register_handler`].
+/// Every invocation starts from the state right after the source ran,
+/// so no state leaks between invocations.
+///
+/// # Example
+/// ```
+/// use hyperlight_python::sandbox::{PyValue, SandboxBuilder};
+///
+/// fn main() -> hyperlight_host::Result<()> {
+///     let proto_sbox = SandboxBuilder::new()
+///         .build()?;
+///
+///     let sandbox = proto_sbox.load_runtime()?;
+///     let sandbox = sandbox.get_loaded_sandbox()?;
+///
+///     let source = r#"
+/// def handle(event):
+///     return {"greeting": "Hello, " + event["name"]}
+/// "#;
+///     let mut handler = sandbox.register_handler(source.to_string())?;
+///
+///     let event = PyValue::Dict(vec![("name".into(), "World".into())]);
+///     let response = handler.invoke(event)?;
+///     assert_eq!(
+///         response,
+///         PyValue::Dict(vec![("greeting".into(), "Hello, World".into())])
+///     );
+///     Ok(())
+/// }
+/// ```
+pub struct PyHandler {
+    /// Sandbox with the handler defined
+    sandbox: LoadedPySandbox,
+    /// Snapshot of the state right after the handler source ran
+    snapshot: Snapshot,
+    /// Whether the state changed since the snapshot was taken or restored
+    dirty: bool,
+}
+
+impl PyHandler {
+    /// Create a new [`PyHandler`]
+    /// # Arguments
+    /// * `sandbox` - The sandbox with the handler defined
+    /// * `snapshot` - The snapshot of the state right after the handler source ran
+    pub(super) fn new(sandbox: LoadedPySandbox, snapshot: Snapshot) -> Self {
+        Self {
+            sandbox,
+            snapshot,
+            dirty: false,
+        }
+    }
+
+    /// Invoke the handler with an event.
+    /// The sandbox is first restored to the state right after the handler source ran,
+    /// which also heals it if a previous invocation poisoned it.
+    /// # Arguments
+    /// * `event` - The event passed to the `handle` function
+    /// # Returns
+    /// * `Result<PyValue>` - The return value of the `handle` function. Returns an error if
+    /// it raised an exception or its return value can't be converted to a [`PyValue`].
+    pub fn invoke(&mut self, event: PyValue) -> Result<PyValue> {
+        if self.dirty || self.sandbox.poisoned() {
+            self.sandbox
+                .restore(&self.snapshot)
+                .map_err(|e| new_error!("Could not reset handler state: {:?}", e))?;
+        }
+
+        self.dirty = true;
+        self.sandbox.call_function(HANDLER_FUNCTION, &[event])
+    }
+
+    /// Returns whether the sandbox is poisoned.
+    /// A poisoned handler heals itself on the next [`PyHandler::invoke`].
+    pub fn poisoned(&self) -> bool {
+        self.sandbox.poisoned()
+    }
+
+    /// Remove the handler and return to the [`LoadedPySandbox`] it was registered with,
+    /// in the state it was in right after the Python runtime was loaded.
+    /// # Errors
+    /// Returns a [`TransitionError`] carrying this handler back if the sandbox could not
+    /// be restored.
+    pub fn unregister(mut self) -> std::result::Result<LoadedPySandbox, TransitionError<Self>> {
+        if let Err(e) = self.sandbox.restore_loaded() {
+            return Err(TransitionError::new(self, e));
+        }
+
+        Ok(self.sandbox)
+    }
+}
@@ -1,7 +1,9 @@
 use hyperlight_host::sandbox::snapshot::Snapshot;
 use hyperlight_host::{MultiUseSandbox, Result, new_error};
 
-use crate::sandbox::LoadedPySandbox;
+use crate::sandbox::runtime_config::RuntimeConfig;
+use crate::sandbox::sandbox_factory::SandboxFactory;
+use crate::sandbox::{GuestInfo, LoadedPySandbox, TransitionError};
 
 /// Python sandbox without the Python runtime loaded.
 /// This sandbox allows initializing the Python runtime and obtaining a [`LoadedPySandbox`]
@@ -37,43 +39,106 @@ pub struct PySandbox {
     pub(super) inner: MultiUseSandbox,
     /// Snapshot of the initial state
     snapshot: Snapshot,
+    /// Factory the inner sandbox was created with
+    factory: SandboxFactory,
+    /// Settings applied once the Python runtime is initialized
+    config: RuntimeConfig,
 }
 
 impl PySandbox {
-    /// Create a new [`PySandbox`] from a [`MultiUseSandbox`]
-    /// Creates a snapshot of the initial state for resetting purposes.
+    /// Create a new [`PySandbox`] from a [`MultiUseSandbox`] without the Python runtime loaded
+    /// and a [`Snapshot`] of its state, used for resetting purposes.
+    /// Also used for unloading the Python runtime, once the sandbox was restored to the snapshot.
     ///
     /// # Arguments
     /// * `inner` - The inner multi-use sandbox
-    pub(super) fn new(mut inner: MultiUseSandbox) -> Result<Self> {
-        let snapshot = inner.snapshot()?;
-        Ok(Self { inner, snapshot })
-    }
-
-    /// Create a new [`PySandbox`] from a loaded [`MultiUseSandbox`] and a [`Snapshot`]
-    /// Restores the sandbox to the given snapshot.
-    /// Used for unloading the Python runtime.
-    /// # Arguments
-    /// * `inner` - The inner multi-use sandbox
-    /// * `snapshot` - The snapshot to restore
-    pub(super) fn from_loaded(mut inner: MultiUseSandbox, snapshot: Snapshot) -> Result<Self> {
-        inner.restore(&snapshot.clone())?;
-        Ok(Self { inner, snapshot })
+    /// * `snapshot` - The snapshot of the initial state
+    /// * `factory` - The factory the inner sandbox was created with
+    /// * `config` - Settings applied once the Python runtime is initialized
+    pub(super) fn new(
+        inner: MultiUseSandbox,
+        snapshot: Snapshot,
+        factory: SandboxFactory,
+        config: RuntimeConfig,
+    ) -> Self {
+        Self {
+            inner,
+            snapshot,
+            factory,
+            config,
+        }
     }
 
     /// Initialize the Python runtime and obtain a [`LoadedPySandbox`].
     ///
     /// # Returns
-    /// * `Result<LoadedPySandbox>` - The loaded Python sandbox.
+    /// * `Result<LoadedPySandbox, TransitionError<PySandbox>>` - The loaded Python sandbox.
     ///
     /// # Errors
-    /// Returns an error if the Python runtime could not be initialized.
-    pub fn get_loaded_sandbox(mut self) -> Result<LoadedPySandbox> {
-        self.inner
-            .call::<bool>("init_python", ())
+    /// Returns a [`TransitionError`] carrying this sandbox back if the Python runtime could
+    /// not be initialized, including when the guest binary reports another ABI version than
+    /// this crate expects, see [`PySandbox::guest_info`]. The sandbox is restored to its initial
+    /// state when possible, otherwise it is left as is and [`PySandbox::poisoned`] tells
+    /// whether it can be used.
+    pub fn get_loaded_sandbox(
+        mut self,
+    ) -> std::result::Result<LoadedPySandbox, TransitionError<Self>> {
+        let loaded_snapshot =
+            Self::initialize(&mut self.inner, &self.config).and_then(|()| self.inner.snapshot());
+
+        match loaded_snapshot {
+            Ok(loaded_snapshot) => Ok(LoadedPySandbox::new(
+                self.inner,
+                self.snapshot,
+                loaded_snapshot,
+                self.factory,
+                self.config,
+            )),
+            Err(e) => {
+                // Drop any partially initialized runtime so the transition can be retried
+                let _ = self.inner.restore(&self.snapshot);
+                Err(TransitionError::new(self, e))
+            }
+        }
+    }
+
+    /// Check the ABI version of the guest, initialize the Python runtime in a sandbox
+    /// and apply the runtime settings
+    /// # Arguments
+    /// * `inner` - The multi-use sandbox without the Python runtime initialized
+    /// * `config` - Settings applied once the Python runtime is initialized
+    pub(super) fn initialize(inner: &mut MultiUseSandbox, config: &RuntimeConfig) -> Result<()> {
+        GuestInfo::query(inner)?.check()?;
+
+        let initialized = inner
+            .call::<bool>("init_python", config.stack_size)
             .map_err(|e| new_error!("Could not initialize Python runtime: {:?}", e))?;
+        if !initialized {
+            return Err(new_error!("Could not initialize Python runtime"));
+        }
+
+        config
+            .apply(inner)
+            .map_err(|e| new_error!("Could not configure Python runtime: {:?}", e))
+    }
 
-        LoadedPySandbox::new(self.inner, self.snapshot)
+    /// Description of the guest binary: its ABI version, MicroPython version, features
+    /// and heap configuration. [`PySandbox::get_loaded_sandbox`] checks the ABI version.
+    ///
+    /// # Example
+    /// ```
+    /// use hyperlight_python::sandbox::SandboxBuilder;
+    ///
+    /// fn main() -> hyperlight_host::Result<()> {
+    ///     let mut sandbox = SandboxBuilder::new().build()?.load_runtime()?;
+    ///
+    ///     let info = sandbox.guest_info()?;
+    ///     assert!(info.micropython_version.starts_with("1."));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn guest_info(&mut self) -> Result<GuestInfo> {
+        GuestInfo::query(&mut self.inner)
     }
 
     /// Returns whether the sandbox is poisoned.
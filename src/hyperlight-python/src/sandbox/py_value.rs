@@ -0,0 +1,451 @@
+use hyperlight_host::{Result, new_error};
+
+/// Tags of the encoded values, must match `micropython-lib/stubs/micropython_stubs.c`.
+/// Integers, floats and lengths are little-endian; lengths and counts are `u32`.
+/// Ints that don't fit an `i64` are sent as decimal strings (`TAG_BIG_INT`).
+const TAG_NONE: u8 = 0;
+const TAG_FALSE: u8 = 1;
+const TAG_TRUE: u8 = 2;
+const TAG_INT: u8 = 3;
+const TAG_FLOAT: u8 = 4;
+const TAG_STR: u8 = 5;
+const TAG_BYTES: u8 = 6;
+const TAG_LIST: u8 = 7;
+const TAG_TUPLE: u8 = 8;
+const TAG_DICT: u8 = 9;
+const TAG_BIG_INT: u8 = 10;
+
+/// Status of a guest response holding a value
+const STATUS_OK: u8 = 0;
+/// Status of a guest response holding the text of the exception raised
+const STATUS_EXCEPTION: u8 = 1;
+
+/// A Python value exchanged with the sandbox, e.g. by
+/// [`LoadedPySandbox::eval`](crate::sandbox::LoadedPySandbox::eval) and
+/// [`LoadedPySandbox::call_function`](crate::sandbox::LoadedPySandbox::call_function).
+/// Converting a Python object of any other type raises a `TypeError` in the guest.
+///
+/// # Example
+/// ```
+/// use hyperlight_python::sandbox::{PyValue, SandboxBuilder};
+///
+/// fn main() -> hyperlight_host::Result<()> {
+///     let proto_sbox = SandboxBuilder::new()
+///         .build()?;
+///
+///     let sandbox = proto_sbox.load_runtime()?;
+///     let mut sandbox = sandbox.get_loaded_sandbox()?;
+///
+///     assert_eq!(sandbox.eval("2**62")?, PyValue::Int(1 << 62));
+///     assert_eq!(
+///         sandbox.eval("2**100")?,
+///         PyValue::BigInt("1267650600228229401496703205376".to_string())
+///     );
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum PyValue {
+    /// `None`
+    None,
+    /// `bool`
+    Bool(bool),
+    /// `int` that fits an `i64`
+    Int(i64),
+    /// `int` too large for an `i64`, as its decimal representation
+    BigInt(String),
+    /// `float`
+    Float(f64),
+    /// `str`
+    Str(String),
+    /// `bytes`
+    Bytes(Vec<u8>),
+    /// `list`
+    List(Vec<PyValue>),
+    /// `tuple`
+    Tuple(Vec<PyValue>),
+    /// `dict`, in iteration order
+    Dict(Vec<(PyValue, PyValue)>),
+}
+
+impl PyValue {
+    /// Encode the value in the format decoded by the guest
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.encode_into(&mut buf);
+
+        buf
+    }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        match self {
+            PyValue::None => buf.push(TAG_NONE),
+            PyValue::Bool(false) => buf.push(TAG_FALSE),
+            PyValue::Bool(true) => buf.push(TAG_TRUE),
+            PyValue::Int(value) => {
+                buf.push(TAG_INT);
+                buf.extend_from_slice(&value.to_le_bytes());
+            }
+            PyValue::BigInt(digits) => encode_big_int(buf, digits),
+            PyValue::Float(value) => {
+                buf.push(TAG_FLOAT);
+                buf.extend_from_slice(&value.to_le_bytes());
+            }
+            PyValue::Str(value) => {
+                encode_len(buf, TAG_STR, value.len());
+                buf.extend_from_slice(value.as_bytes());
+            }
+            PyValue::Bytes(value) => {
+                encode_len(buf, TAG_BYTES, value.len());
+                buf.extend_from_slice(value);
+            }
+            PyValue::List(items) | PyValue::Tuple(items) => {
+                let tag = match self {
+                    PyValue::List(_) => TAG_LIST,
+                    _ => TAG_TUPLE,
+                };
+                encode_len(buf, tag, items.len());
+                for item in items {
+                    item.encode_into(buf);
+                }
+            }
+            PyValue::Dict(entries) => {
+                encode_len(buf, TAG_DICT, entries.len());
+                for (key, value) in entries {
+                    key.encode_into(buf);
+                    value.encode_into(buf);
+                }
+            }
+        }
+    }
+
+    /// Decode a value encoded by the guest
+    /// # Arguments
+    /// * `bytes` - The encoded value
+    pub(crate) fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader(bytes);
+        let value = reader.value()?;
+
+        if !reader.0.is_empty() {
+            return Err(new_error!("Invalid value from guest: trailing bytes"));
+        }
+
+        Ok(value)
+    }
+
+    /// Decode the response of the `eval_python` and `call_python` guest functions:
+    /// a status byte followed by the encoded value or the text of the exception raised.
+    /// # Arguments
+    /// * `response` - The response of the guest function
+    /// # Errors
+    /// Returns an error holding the exception text if the Python code raised an exception.
+    pub(crate) fn from_response(response: &[u8]) -> Result<Self> {
+        match response.split_first() {
+            Some((&STATUS_OK, value)) => Self::decode(value),
+            Some((&STATUS_EXCEPTION, text)) => Err(new_error!(
+                "Python exception: {}",
+                String::from_utf8_lossy(text).trim_end()
+            )),
+            Some((status, _)) => Err(new_error!("Invalid response status from guest: {}", status)),
+            None => Err(new_error!("Python runtime not initialized")),
+        }
+    }
+}
+
+/// Write a tag followed by a length or a count
+fn encode_len(buf: &mut Vec<u8>, tag: u8, len: usize) {
+    buf.push(tag);
+    buf.extend_from_slice(&(len as u32).to_le_bytes());
+}
+
+/// Write an int as its decimal representation
+fn encode_big_int(buf: &mut Vec<u8>, digits: &str) {
+    encode_len(buf, TAG_BIG_INT, digits.len());
+    buf.extend_from_slice(digits.as_bytes());
+}
+
+/// Encoded value being decoded
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.0.len() {
+            return Err(new_error!("Invalid value from guest: unexpected end"));
+        }
+
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(self.take(N)?);
+
+        Ok(bytes)
+    }
+
+    /// Read a length or a count, which can't exceed the remaining bytes
+    fn take_len(&mut self) -> Result<usize> {
+        let len = u32::from_le_bytes(self.array()?) as usize;
+
+        if len > self.0.len() {
+            return Err(new_error!("Invalid value from guest: length out of bounds"));
+        }
+
+        Ok(len)
+    }
+
+    fn values(&mut self, count: usize) -> Result<Vec<PyValue>> {
+        (0..count).map(|_| self.value()).collect()
+    }
+
+    fn value(&mut self) -> Result<PyValue> {
+        let [tag] = self.array()?;
+
+        Ok(match tag {
+            TAG_NONE => PyValue::None,
+            TAG_FALSE => PyValue::Bool(false),
+            TAG_TRUE => PyValue::Bool(true),
+            TAG_INT => PyValue::Int(i64::from_le_bytes(self.array()?)),
+            TAG_BIG_INT => {
+                let len = self.take_len()?;
+                let digits = std::str::from_utf8(self.take(len)?)
+                    .ok()
+                    .filter(|digits| {
+                        let unsigned = digits.strip_prefix('-').unwrap_or(digits);
+                        !unsigned.is_empty() && unsigned.bytes().all(|b| b.is_ascii_digit())
+                    })
+                    .ok_or_else(|| new_error!("Invalid int from guest"))?;
+
+                digits
+                    .parse()
+                    .map_or_else(|_| PyValue::BigInt(digits.to_string()), PyValue::Int)
+            }
+            TAG_FLOAT => PyValue::Float(f64::from_le_bytes(self.array()?)),
+            TAG_STR => {
+                let len = self.take_len()?;
+                let bytes = self.take(len)?.to_vec();
+                PyValue::Str(
+                    String::from_utf8(bytes)
+                        .map_err(|e| new_error!("Invalid string from guest: {}", e))?,
+                )
+            }
+            TAG_BYTES => {
+                let len = self.take_len()?;
+                PyValue::Bytes(self.take(len)?.to_vec())
+            }
+            TAG_LIST => {
+                let len = self.take_len()?;
+                PyValue::List(self.values(len)?)
+            }
+            TAG_TUPLE => {
+                let len = self.take_len()?;
+                PyValue::Tuple(self.values(len)?)
+            }
+            TAG_DICT => {
+                let len = self.take_len()?;
+                let entries = (0..len)
+                    .map(|_| Ok((self.value()?, self.value()?)))
+                    .collect::<Result<_>>()?;
+                PyValue::Dict(entries)
+            }
+            tag => return Err(new_error!("Invalid value from guest: unknown tag {}", tag)),
+        })
+    }
+}
+
+impl From<bool> for PyValue {
+    fn from(value: bool) -> Self {
+        PyValue::Bool(value)
+    }
+}
+
+impl From<i64> for PyValue {
+    fn from(value: i64) -> Self {
+        PyValue::Int(value)
+    }
+}
+
+impl From<i128> for PyValue {
+    /// [`PyValue::Int`] if the value fits an `i64`, [`PyValue::BigInt`] otherwise
+    fn from(value: i128) -> Self {
+        i64::try_from(value).map_or_else(|_| PyValue::BigInt(value.to_string()), PyValue::Int)
+    }
+}
+
+impl From<f64> for PyValue {
+    fn from(value: f64) -> Self {
+        PyValue::Float(value)
+    }
+}
+
+impl From<&str> for PyValue {
+    fn from(value: &str) -> Self {
+        PyValue::Str(value.to_string())
+    }
+}
+
+impl From<String> for PyValue {
+    fn from(value: String) -> Self {
+        PyValue::Str(value)
+    }
+}
+
+impl From<Vec<u8>> for PyValue {
+    fn from(value: Vec<u8>) -> Self {
+        PyValue::Bytes(value)
+    }
+}
+
+impl From<Vec<PyValue>> for PyValue {
+    fn from(value: Vec<PyValue>) -> Self {
+        PyValue::List(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(value: PyValue) {
+        assert_eq!(PyValue::decode(&value.encode()).unwrap(), value);
+    }
+
+    #[test]
+    fn scalars_round_trip() {
+        round_trip(PyValue::None);
+        round_trip(PyValue::Bool(false));
+        round_trip(PyValue::Bool(true));
+        round_trip(PyValue::Int(0));
+        round_trip(PyValue::Int(i64::MIN));
+        round_trip(PyValue::Int(i64::MAX));
+        round_trip(PyValue::BigInt(
+            "1267650600228229401496703205376".to_string(),
+        ));
+        round_trip(PyValue::BigInt("-9223372036854775809".to_string()));
+        round_trip(PyValue::Float(-1.5));
+        round_trip(PyValue::Str("héllo".to_string()));
+        round_trip(PyValue::Str(String::new()));
+        round_trip(PyValue::Bytes(vec![0, 1, 255]));
+    }
+
+    #[test]
+    fn containers_round_trip() {
+        round_trip(PyValue::List(vec![]));
+        round_trip(PyValue::Tuple(vec![PyValue::Int(1), PyValue::None]));
+        round_trip(PyValue::Dict(vec![
+            (
+                PyValue::from("list"),
+                PyValue::List(vec![PyValue::Bool(true)]),
+            ),
+            (
+                PyValue::Tuple(vec![PyValue::Int(1), PyValue::Int(2)]),
+                PyValue::Dict(vec![(PyValue::from("nested"), PyValue::Float(0.5))]),
+            ),
+        ]));
+    }
+
+    #[test]
+    fn big_int_fitting_an_i64_decodes_as_int() {
+        let mut buf = Vec::new();
+        encode_big_int(&mut buf, "-42");
+
+        assert_eq!(PyValue::decode(&buf).unwrap(), PyValue::Int(-42));
+    }
+
+    #[test]
+    fn from_i128_picks_int_or_big_int() {
+        assert_eq!(PyValue::from(i128::from(i64::MIN)), PyValue::Int(i64::MIN));
+        assert_eq!(
+            PyValue::from(1i128 << 100),
+            PyValue::BigInt("1267650600228229401496703205376".to_string())
+        );
+    }
+
+    #[test]
+    fn truncated_values_are_rejected() {
+        let value = PyValue::Dict(vec![(
+            PyValue::from("key"),
+            PyValue::List(vec![PyValue::Int(7), PyValue::Bytes(vec![1, 2, 3])]),
+        )]);
+        let bytes = value.encode();
+
+        for len in 0..bytes.len() {
+            assert!(
+                PyValue::decode(&bytes[..len]).is_err(),
+                "prefix of {} bytes",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn trailing_bytes_are_rejected() {
+        let mut bytes = PyValue::Int(1).encode();
+        bytes.push(TAG_NONE);
+
+        assert!(PyValue::decode(&bytes).is_err());
+    }
+
+    #[test]
+    fn unknown_tags_are_rejected() {
+        let err = PyValue::decode(&[42]).unwrap_err();
+
+        assert!(err.to_string().contains("unknown tag 42"), "{}", err);
+    }
+
+    #[test]
+    fn oversized_lengths_are_rejected() {
+        for tag in [
+            TAG_STR,
+            TAG_BYTES,
+            TAG_LIST,
+            TAG_TUPLE,
+            TAG_DICT,
+            TAG_BIG_INT,
+        ] {
+            let mut bytes = vec![tag];
+            bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+            bytes.extend_from_slice(b"12");
+
+            let err = PyValue::decode(&bytes).unwrap_err();
+            assert!(err.to_string().contains("length out of bounds"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn invalid_contents_are_rejected() {
+        for digits in ["", "-", "12a", "+1", "1.5"] {
+            let mut buf = Vec::new();
+            encode_big_int(&mut buf, digits);
+            assert!(PyValue::decode(&buf).is_err(), "{:?}", digits);
+        }
+
+        let mut buf = Vec::new();
+        encode_len(&mut buf, TAG_STR, 2);
+        buf.extend_from_slice(&[0xc3, 0x28]);
+        assert!(PyValue::decode(&buf).is_err());
+    }
+
+    #[test]
+    fn responses_carry_the_value_or_the_exception() {
+        let mut response = vec![STATUS_OK];
+        response.extend(PyValue::Int(3).encode());
+        assert_eq!(PyValue::from_response(&response).unwrap(), PyValue::Int(3));
+
+        let mut response = vec![STATUS_EXCEPTION];
+        response.extend_from_slice(b"ZeroDivisionError: divide by zero\n");
+        let err = PyValue::from_response(&response).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("ZeroDivisionError: divide by zero"),
+            "{}",
+            err
+        );
+
+        assert!(PyValue::from_response(&[]).is_err());
+        assert!(PyValue::from_response(&[7]).is_err());
+    }
+}
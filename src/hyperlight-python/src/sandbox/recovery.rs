@@ -0,0 +1,48 @@
+use std::fmt;
+use std::sync::Arc;
+
+/// What a [`LoadedPySandbox`](crate::sandbox::LoadedPySandbox) does before its next call
+/// once a run poisoned it, e.g. because the guest aborted or the run was cancelled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RecoveryPolicy {
+    /// Leave the sandbox poisoned, every following call fails
+    #[default]
+    Never,
+    /// Restore the snapshot taken right after the Python runtime was loaded
+    RestoreLastSnapshot,
+    /// Build a new sandbox from scratch and load the Python runtime into it
+    RebuildFromScratch,
+}
+
+/// Emitted when a poisoned sandbox recovered according to its [`RecoveryPolicy`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveryEvent {
+    /// The policy used to recover
+    pub policy: RecoveryPolicy,
+    /// The error of the run that poisoned the sandbox
+    pub reason: String,
+}
+
+/// Callback notified of every [`RecoveryEvent`]
+#[derive(Clone)]
+pub(crate) struct RecoveryHandler(Arc<dyn Fn(&RecoveryEvent) + Send + Sync>);
+
+impl RecoveryHandler {
+    /// Create a new [`RecoveryHandler`]
+    /// # Arguments
+    /// * `handler` - The callback to notify
+    pub(crate) fn new(handler: impl Fn(&RecoveryEvent) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(handler))
+    }
+
+    /// Notify the callback of an event
+    pub(crate) fn notify(&self, event: &RecoveryEvent) {
+        (self.0)(event)
+    }
+}
+
+impl fmt::Debug for RecoveryHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RecoveryHandler")
+    }
+}
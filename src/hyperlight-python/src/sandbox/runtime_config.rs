@@ -0,0 +1,67 @@
+use hyperlight_host::{MultiUseSandbox, Result, new_error};
+
+use crate::sandbox::module_policy::ModulePolicy;
+use crate::sandbox::recovery::RecoveryHandler;
+use crate::sandbox::sandbox_builder::{DEFAULT_REGEX_BACKTRACK_LIMIT, DEFAULT_STACK_SIZE};
+use crate::sandbox::{BuiltinPolicy, RecoveryPolicy};
+
+/// Per-sandbox settings applied by the guest once the Python runtime is initialized.
+/// Collected by the [`SandboxBuilder`](crate::sandbox::SandboxBuilder) and carried along
+/// the sandbox states so they can be applied again every time the runtime is loaded.
+#[derive(Debug, Clone)]
+pub(crate) struct RuntimeConfig {
+    /// Size of the guest stack, from which the guest derives its recursion limit
+    pub(crate) stack_size: u64,
+    /// Modules that scripts are allowed to import
+    pub(crate) module_policy: ModulePolicy,
+    /// Builtins restricted for scripts
+    pub(crate) builtin_policy: Option<BuiltinPolicy>,
+    /// Maximum number of backtracking steps of a regular expression match, 0 for no limit
+    pub(crate) regex_backtrack_limit: u64,
+    /// Whether the clock is virtual, the `random` module has a fixed seed and the host
+    /// calls are recorded
+    pub(crate) deterministic: bool,
+    /// Whether every script run starts from the freshly loaded runtime
+    pub(crate) restore_each_run: bool,
+    /// How a poisoned sandbox recovers before its next run
+    pub(crate) recovery_policy: RecoveryPolicy,
+    /// Callback notified every time a poisoned sandbox recovers
+    pub(crate) recovery_handler: Option<RecoveryHandler>,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            stack_size: DEFAULT_STACK_SIZE,
+            module_policy: ModulePolicy::default(),
+            builtin_policy: None,
+            regex_backtrack_limit: DEFAULT_REGEX_BACKTRACK_LIMIT,
+            deterministic: false,
+            restore_each_run: false,
+            recovery_policy: RecoveryPolicy::default(),
+            recovery_handler: None,
+        }
+    }
+}
+
+impl RuntimeConfig {
+    /// Apply the settings to a sandbox whose Python runtime was just initialized
+    /// # Arguments
+    /// * `sandbox` - The sandbox with the Python runtime initialized
+    pub(crate) fn apply(&self, sandbox: &mut MultiUseSandbox) -> Result<()> {
+        // The builtin policy is applied first since it imports the builtins module
+        if let Some(builtin_policy) = &self.builtin_policy {
+            builtin_policy.apply(sandbox)?;
+        }
+
+        self.module_policy.apply(sandbox)?;
+
+        let applied =
+            sandbox.call::<bool>("set_regex_backtrack_limit", self.regex_backtrack_limit)?;
+        if !applied {
+            return Err(new_error!("Guest rejected the regex backtrack limit"));
+        }
+
+        Ok(())
+    }
+}
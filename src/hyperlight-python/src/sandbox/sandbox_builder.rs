@@ -1,20 +1,48 @@
-use hyperlight_host::GuestBinary;
+use std::path::PathBuf;
+use std::time::{Duration, UNIX_EPOCH};
+
 use hyperlight_host::HyperlightError;
-use hyperlight_host::Result;
 use hyperlight_host::is_hypervisor_present;
 use hyperlight_host::sandbox::SandboxConfiguration;
 #[cfg(feature = "gdb")]
 use hyperlight_host::sandbox::config::DebugInfo;
+use hyperlight_host::{GuestBinary, Result, new_error};
 
 use crate::HostPrintFn;
+use crate::sandbox::batch;
+use crate::sandbox::clock::DETERMINISTIC_START_SECS;
+use crate::sandbox::host_calls::{HostCallReplay, HostCalls};
+use crate::sandbox::module_policy::ModulePolicy;
 use crate::sandbox::proto_py_sandbox::ProtoPySandbox;
+use crate::sandbox::recovery::RecoveryHandler;
+use crate::sandbox::runtime_config::RuntimeConfig;
+use crate::sandbox::sandbox_factory::{GuestImage, SandboxFactory};
+use crate::sandbox::{BatchResult, BuiltinPolicy, ClockPolicy, RecoveryEvent, RecoveryPolicy};
+
+/// Default size of the sandbox stack (128 kB)
+pub(crate) const DEFAULT_STACK_SIZE: u64 = 128 * 1024;
+/// Default size of the sandbox heap (512 kB)
+const DEFAULT_HEAP_SIZE: u64 = 512 * 1024;
+/// Default maximum number of backtracking steps of a regular expression match
+pub(crate) const DEFAULT_REGEX_BACKTRACK_LIMIT: u64 = 1_000_000;
 
 /// Sandbox builder for the [`ProtoPySandbox`]
+#[derive(Clone)]
 pub struct SandboxBuilder {
     /// Configuration for the inner sandbox
     cfg: SandboxConfiguration,
+    /// Guest binary replacing the embedded python-host
+    guest_binary: Option<GuestImage>,
     /// Optional host print function
-    host_print_fn: Option<HostPrintFn>,
+    pub(super) host_print_fn: Option<HostPrintFn>,
+    /// Clock read by the `time` module
+    clock_policy: ClockPolicy,
+    /// File the host calls of every run are written to
+    record_host_calls: Option<PathBuf>,
+    /// Recording answering the host calls of every run
+    replay_host_calls: Option<PathBuf>,
+    /// Settings applied once the Python runtime is initialized
+    runtime_cfg: RuntimeConfig,
 }
 
 impl SandboxBuilder {
@@ -32,16 +60,23 @@ impl SandboxBuilder {
     /// ```
     pub fn new() -> Self {
         let mut cfg = SandboxConfiguration::default();
-        cfg.set_stack_size(128 * 1024);
-        cfg.set_heap_size(512 * 1024);
+        cfg.set_stack_size(DEFAULT_STACK_SIZE);
+        cfg.set_heap_size(DEFAULT_HEAP_SIZE);
 
         Self {
             cfg,
+            guest_binary: None,
             host_print_fn: None,
+            clock_policy: ClockPolicy::default(),
+            record_host_calls: None,
+            replay_host_calls: None,
+            runtime_cfg: RuntimeConfig::default(),
         }
     }
 
-    /// Set the stack size for the sandbox
+    /// Set the stack size for the sandbox.
+    /// The Python runtime raises `RuntimeError: maximum recursion depth exceeded`
+    /// before the stack runs out, keeping a margin for handling the exception.
     /// # Arguments
     /// * `size` - Size of the stack in bytes
     ///
@@ -59,6 +94,7 @@ impl SandboxBuilder {
     /// ```
     pub fn with_stack_size(mut self, size: u64) -> Self {
         self.cfg.set_stack_size(size);
+        self.runtime_cfg.stack_size = size;
 
         self
     }
@@ -85,7 +121,39 @@ impl SandboxBuilder {
         self
     }
 
-    /// Set the host print function for the sandbox
+    /// Run a custom guest binary in place of the python-host embedded in this crate,
+    /// e.g. a python-host built with extra frozen or native modules.
+    /// The guest must implement the guest functions of python-host with the same ABI version:
+    /// loading the Python runtime fails with an incompatibility error otherwise.
+    /// Required with the `custom-guest` feature, which doesn't embed python-host.
+    /// # Arguments
+    /// * `binary` - The guest binary, in memory or as a file path read once by [`SandboxBuilder::build`]
+    ///
+    /// # Example
+    /// ```no_run
+    /// use hyperlight_host::GuestBinary;
+    /// use hyperlight_python::sandbox::SandboxBuilder;
+    ///
+    /// fn main() -> hyperlight_host::Result<()> {
+    ///     let proto_sbox = SandboxBuilder::new()
+    ///         .with_guest_binary(GuestBinary::FilePath("guests/python-host".to_string()))
+    ///         .build()?;
+    ///
+    ///     let sandbox = proto_sbox.load_runtime()?;
+    ///     let mut sandbox = sandbox.get_loaded_sandbox()?;
+    ///
+    ///     sandbox.run_script("import mymodule".to_string())?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_guest_binary(mut self, binary: GuestBinary<'static>) -> Self {
+        self.guest_binary = Some(binary.into());
+
+        self
+    }
+
+    /// Set the host print function for the sandbox.
+    /// Without one, the output of the scripts is printed to the host stdout.
     /// # Arguments
     /// * `print_fn` - Function to handle host print calls
     /// # Example
@@ -112,6 +180,346 @@ impl SandboxBuilder {
         self
     }
 
+    /// Only allow scripts to import the given modules.
+    /// Listing a package also allows its submodules. Importing any other module,
+    /// including built-in and frozen ones, raises an `ImportError`.
+    /// Replaces any previous call to [`SandboxBuilder::allow_modules`] or
+    /// [`SandboxBuilder::deny_modules`].
+    /// # Arguments
+    /// * `modules` - Names of the modules that can be imported
+    ///
+    /// # Example
+    /// ```
+    /// use hyperlight_python::sandbox::SandboxBuilder;
+    ///
+    /// fn main() -> hyperlight_host::Result<()> {
+    ///     let sandbox = SandboxBuilder::new()
+    ///         .allow_modules(["array"])
+    ///         .build()?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn allow_modules<I, S>(mut self, modules: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.runtime_cfg.module_policy =
+            ModulePolicy::Allow(modules.into_iter().map(Into::into).collect());
+
+        self
+    }
+
+    /// Prevent scripts from importing the given modules.
+    /// Listing a package also denies its submodules. Importing a denied module,
+    /// including built-in and frozen ones, raises an `ImportError`.
+    /// Replaces any previous call to [`SandboxBuilder::allow_modules`] or
+    /// [`SandboxBuilder::deny_modules`].
+    /// # Arguments
+    /// * `modules` - Names of the modules that cannot be imported
+    ///
+    /// # Example
+    /// ```
+    /// use hyperlight_python::sandbox::SandboxBuilder;
+    ///
+    /// fn main() -> hyperlight_host::Result<()> {
+    ///     let sandbox = SandboxBuilder::new()
+    ///         .deny_modules(["gc", "array"])
+    ///         .build()?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn deny_modules<I, S>(mut self, modules: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.runtime_cfg.module_policy =
+            ModulePolicy::Deny(modules.into_iter().map(Into::into).collect());
+
+        self
+    }
+
+    /// Restrict the builtins available to scripts in the sandbox
+    /// # Arguments
+    /// * `policy` - The [`BuiltinPolicy`] applied once the Python runtime is initialized
+    ///
+    /// # Example
+    /// ```
+    /// use hyperlight_python::sandbox::{BuiltinPolicy, SandboxBuilder};
+    ///
+    /// fn main() -> hyperlight_host::Result<()> {
+    ///     let sandbox = SandboxBuilder::new()
+    ///         .with_builtin_policy(BuiltinPolicy::restricted())
+    ///         .build()?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_builtin_policy(mut self, policy: BuiltinPolicy) -> Self {
+        self.runtime_cfg.builtin_policy = Some(policy);
+
+        self
+    }
+
+    /// Set the clock read by the `time` module of the sandbox.
+    /// Defaults to [`ClockPolicy::Real`].
+    /// # Arguments
+    /// * `policy` - The [`ClockPolicy`] to use
+    ///
+    /// # Example
+    /// ```
+    /// use std::time::{Duration, UNIX_EPOCH};
+    ///
+    /// use hyperlight_python::sandbox::{ClockPolicy, PyValue, SandboxBuilder};
+    ///
+    /// fn main() -> hyperlight_host::Result<()> {
+    ///     let proto_sbox = SandboxBuilder::new()
+    ///         .with_clock_policy(ClockPolicy::Virtual(UNIX_EPOCH + Duration::from_secs(60)))
+    ///         .build()?;
+    ///
+    ///     let sandbox = proto_sbox.load_runtime()?;
+    ///     let mut sandbox = sandbox.get_loaded_sandbox()?;
+    ///
+    ///     sandbox.run_script("import time\ntime.sleep(30)".to_string())?;
+    ///     assert_eq!(sandbox.eval("time.time()")?, PyValue::Float(90.0));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_clock_policy(mut self, policy: ClockPolicy) -> Self {
+        self.clock_policy = policy;
+
+        self
+    }
+
+    /// Make script runs replayable: running the same script twice gives the same output.
+    /// In deterministic mode:
+    /// - the clock is virtual, starting at 2000-01-01T00:00:00Z on every run,
+    ///   unless a frozen or virtual [`ClockPolicy`] is set,
+    /// - the `random` module is seeded with a fixed seed at the start of every run, unless
+    ///   [`ExecOptions::seed`](crate::sandbox::ExecOptions::seed) gives one,
+    /// - the host calls of every run are recorded, see
+    ///   [`LoadedPySandbox::host_calls`](crate::sandbox::LoadedPySandbox::host_calls).
+    ///
+    /// Globals defined by a run are kept for the next ones, so object addresses, and thus
+    /// `id()` and the iteration order of sets of objects, may depend on the previous runs:
+    /// combine with [`SandboxBuilder::restore_each_run`] to rule that out.
+    /// MicroPython doesn't randomize the hashes of `str` and `bytes`.
+    /// # Arguments
+    /// * `enabled` - Whether the sandbox is deterministic
+    ///
+    /// # Example
+    /// ```
+    /// use hyperlight_python::sandbox::SandboxBuilder;
+    ///
+    /// fn main() -> hyperlight_host::Result<()> {
+    ///     let proto_sbox = SandboxBuilder::new()
+    ///         .deterministic(true)
+    ///         .build()?;
+    ///
+    ///     let sandbox = proto_sbox.load_runtime()?;
+    ///     let mut sandbox = sandbox.get_loaded_sandbox()?;
+    ///
+    ///     let code = "import random, time\nprint(time.time(), random.random())".to_string();
+    ///     sandbox.run_script(code.clone())?;
+    ///     let first = sandbox.host_calls();
+    ///     sandbox.run_script(code)?;
+    ///     assert_eq!(sandbox.host_calls(), first);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn deterministic(mut self, enabled: bool) -> Self {
+        self.runtime_cfg.deterministic = enabled;
+
+        self
+    }
+
+    /// Start every script run from the state right after the Python runtime was loaded,
+    /// dropping the globals defined by the previous runs.
+    /// Names defined by a script remain visible to
+    /// [`LoadedPySandbox::eval`](crate::sandbox::LoadedPySandbox::eval) and
+    /// [`LoadedPySandbox::call_function`](crate::sandbox::LoadedPySandbox::call_function)
+    /// until the next script run. Off by default.
+    /// # Arguments
+    /// * `enabled` - Whether every script run starts from the freshly loaded runtime
+    ///
+    /// # Example
+    /// ```
+    /// use hyperlight_python::sandbox::{PyValue, SandboxBuilder};
+    ///
+    /// fn main() -> hyperlight_host::Result<()> {
+    ///     let proto_sbox = SandboxBuilder::new()
+    ///         .restore_each_run(true)
+    ///         .build()?;
+    ///
+    ///     let sandbox = proto_sbox.load_runtime()?;
+    ///     let mut sandbox = sandbox.get_loaded_sandbox()?;
+    ///
+    ///     sandbox.run_script("answer = 42".to_string())?;
+    ///     assert_eq!(sandbox.eval("answer")?, PyValue::Int(42));
+    ///
+    ///     sandbox.run_script("seen = 'answer' in globals()".to_string())?;
+    ///     assert_eq!(sandbox.eval("seen")?, PyValue::Bool(false));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn restore_each_run(mut self, enabled: bool) -> Self {
+        self.runtime_cfg.restore_each_run = enabled;
+
+        self
+    }
+
+    /// Record the calls the guest makes to the host functions (`HostPrint`, the clock
+    /// and the seed of the `random` module) with their arguments and responses.
+    /// At the end of every run, i.e. script run or expression or function call made with
+    /// [`LoadedPySandbox::eval`](crate::sandbox::LoadedPySandbox::eval) and friends,
+    /// the calls of the run are written to `path` as JSON lines, replacing the previous run's, e.g.
+    /// `{"function":"HostTimeNs","args":[],"response":946684800000000000}`.
+    /// Pass the file to [`SandboxBuilder::replay_host_calls`] to reproduce the run.
+    /// [`SandboxBuilder::run_batch`] rejects a builder recording the host calls.
+    /// # Arguments
+    /// * `path` - The file the host calls are written to
+    ///
+    /// # Example
+    /// ```
+    /// use hyperlight_python::sandbox::SandboxBuilder;
+    ///
+    /// fn main() -> hyperlight_host::Result<()> {
+    ///     let proto_sbox = SandboxBuilder::new()
+    ///         .record_host_calls("host_calls.jsonl")
+    ///         .build()?;
+    ///
+    ///     let sandbox = proto_sbox.load_runtime()?;
+    ///     let mut sandbox = sandbox.get_loaded_sandbox()?;
+    ///
+    ///     sandbox.run_script("import time\nprint(time.time())".to_string())?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn record_host_calls(mut self, path: impl Into<PathBuf>) -> Self {
+        self.record_host_calls = Some(path.into());
+
+        self
+    }
+
+    /// Answer the host calls of every script run from a recording written by
+    /// [`SandboxBuilder::record_host_calls`], in place of the host functions:
+    /// the clock, the seed of the `random` module and the print function are not called.
+    /// Every call must match the next recorded one, function and arguments,
+    /// so a script printing something else than during the recording diverges.
+    /// A run that diverged from the recording, or made fewer calls, returns an error,
+    /// while the diverging clock or `random` call raises an `OSError` into the script.
+    /// The recording is rewound at the start of every run: expressions and function
+    /// calls made with [`LoadedPySandbox::eval`](crate::sandbox::LoadedPySandbox::eval)
+    /// and friends are runs of their own, replayed against the whole recording too.
+    /// # Arguments
+    /// * `path` - The recording
+    ///
+    /// # Example
+    /// ```
+    /// use hyperlight_python::sandbox::SandboxBuilder;
+    ///
+    /// fn main() -> hyperlight_host::Result<()> {
+    ///     let code = "import random, time\nprint(time.time(), random.random())".to_string();
+    ///
+    ///     let proto_sbox = SandboxBuilder::new()
+    ///         .record_host_calls("host_calls.jsonl")
+    ///         .build()?;
+    ///     let mut sandbox = proto_sbox.load_runtime()?.get_loaded_sandbox()?;
+    ///     sandbox.run_script(code.clone())?;
+    ///     let recorded = sandbox.host_calls();
+    ///
+    ///     let proto_sbox = SandboxBuilder::new()
+    ///         .replay_host_calls("host_calls.jsonl")
+    ///         .build()?;
+    ///     let mut sandbox = proto_sbox.load_runtime()?.get_loaded_sandbox()?;
+    ///     sandbox.run_script(code)?;
+    ///     assert_eq!(sandbox.host_calls(), recorded);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn replay_host_calls(mut self, path: impl Into<PathBuf>) -> Self {
+        self.replay_host_calls = Some(path.into());
+
+        self
+    }
+
+    /// Limit the number of backtracking steps a single match of the `re` module
+    /// can take. A match going past the limit raises
+    /// `RuntimeError: regex backtrack limit exceeded`, so that pathological patterns
+    /// fail quickly. Defaults to 1,000,000 steps, 0 removes the limit.
+    /// Deeply nested matches raise `RuntimeError: maximum recursion depth exceeded`
+    /// regardless, see [`SandboxBuilder::with_stack_size`].
+    /// # Arguments
+    /// * `limit` - The maximum number of backtracking steps of a match
+    ///
+    /// # Example
+    /// ```
+    /// use hyperlight_python::sandbox::SandboxBuilder;
+    ///
+    /// fn main() -> hyperlight_host::Result<()> {
+    ///     let proto_sbox = SandboxBuilder::new()
+    ///         .with_regex_backtrack_limit(10_000)
+    ///         .build()?;
+    ///
+    ///     let sandbox = proto_sbox.load_runtime()?;
+    ///     let mut sandbox = sandbox.get_loaded_sandbox()?;
+    ///
+    ///     let code = "import re\nre.match('(a|aa)*b', 'a' * 40)".to_string();
+    ///     assert!(!sandbox.run_script(code)?);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_regex_backtrack_limit(mut self, limit: u64) -> Self {
+        self.runtime_cfg.regex_backtrack_limit = limit;
+
+        self
+    }
+
+    /// Set how a [`LoadedPySandbox`](crate::sandbox::LoadedPySandbox) recovers once a run
+    /// poisoned it, e.g. because the guest aborted or the run was cancelled.
+    /// The sandbox recovers before its next run. Defaults to [`RecoveryPolicy::Never`].
+    /// # Arguments
+    /// * `policy` - The [`RecoveryPolicy`] to use
+    ///
+    /// # Example
+    /// ```
+    /// use hyperlight_python::sandbox::{RecoveryPolicy, SandboxBuilder};
+    ///
+    /// fn main() -> hyperlight_host::Result<()> {
+    ///     let sandbox = SandboxBuilder::new()
+    ///         .with_recovery_policy(RecoveryPolicy::RestoreLastSnapshot)
+    ///         .build()?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_recovery_policy(mut self, policy: RecoveryPolicy) -> Self {
+        self.runtime_cfg.recovery_policy = policy;
+
+        self
+    }
+
+    /// Set a callback notified every time a poisoned sandbox recovers
+    /// # Arguments
+    /// * `handler` - Callback receiving the [`RecoveryEvent`]
+    ///
+    /// # Example
+    /// ```
+    /// use hyperlight_python::sandbox::{RecoveryPolicy, SandboxBuilder};
+    ///
+    /// fn main() -> hyperlight_host::Result<()> {
+    ///     let sandbox = SandboxBuilder::new()
+    ///         .with_recovery_policy(RecoveryPolicy::RebuildFromScratch)
+    ///         .on_recovery(|event| eprintln!("sandbox recovered: {}", event.reason))
+    ///         .build()?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn on_recovery(mut self, handler: impl Fn(&RecoveryEvent) + Send + Sync + 'static) -> Self {
+        self.runtime_cfg.recovery_handler = Some(RecoveryHandler::new(handler));
+
+        self
+    }
+
     /// Enable debugging for the sandbox created
     /// # Arguments
     /// * `port` - Port to use for debugging
@@ -135,6 +543,55 @@ impl SandboxBuilder {
         self
     }
 
+    /// Run independent scripts in parallel across `parallelism` sandboxes built
+    /// with this configuration, each on its own worker thread.
+    /// Every script starts from a freshly loaded Python runtime and its output is
+    /// captured in its [`BatchResult`] instead of being passed to the host print function.
+    /// # Arguments
+    /// * `scripts` - The Python scripts to run
+    /// * `parallelism` - Number of sandboxes and worker threads, at least 1
+    /// # Returns
+    /// * `Result<Vec<Result<BatchResult>>>` - The result of every script, in input order.
+    /// Returns an error if one of the sandboxes could not be built, or if the builder
+    /// records the host calls: the workers would all write the same recording.
+    ///
+    /// # Example
+    /// ```
+    /// use hyperlight_python::sandbox::SandboxBuilder;
+    ///
+    /// fn main() -> hyperlight_host::Result<()> {
+    ///     let scripts = vec![
+    ///         "print(6 * 7)".to_string(),
+    ///         "raise ValueError('wrong answer')".to_string(),
+    ///     ];
+    ///
+    ///     let results = SandboxBuilder::new().run_batch(scripts, 2)?;
+    ///
+    ///     let first = results[0].as_ref().unwrap();
+    ///     assert!(first.success);
+    ///     assert_eq!(first.output, "42\n");
+    ///
+    ///     let second = results[1].as_ref().unwrap();
+    ///     assert!(!second.success);
+    ///     assert!(second.exception.as_ref().unwrap().contains("wrong answer"));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn run_batch(
+        &self,
+        scripts: Vec<String>,
+        parallelism: usize,
+    ) -> Result<Vec<Result<BatchResult>>> {
+        if let Some(path) = &self.record_host_calls {
+            return Err(new_error!(
+                "Cannot record the host calls of a batch to {}",
+                path.display()
+            ));
+        }
+
+        batch::run_batch(self, scripts, parallelism)
+    }
+
     /// Use the builder to generate the [`ProtoPySandbox`]
     ///
     /// # Example
@@ -151,8 +608,129 @@ impl SandboxBuilder {
         if !is_hypervisor_present() {
             return Err(HyperlightError::NoHypervisorFound());
         }
-        let guest_binary = GuestBinary::Buffer(super::PYHOST);
+        let guest_binary = self
+            .guest_binary
+            .or_else(|| super::PYHOST.map(GuestImage::Buffer))
+            .ok_or_else(|| {
+                new_error!(
+                    "No guest binary: the custom-guest feature requires SandboxBuilder::with_guest_binary"
+                )
+            })?
+            .load()?;
+        let deterministic = self.runtime_cfg.deterministic;
+        let clock_policy = match self.clock_policy {
+            ClockPolicy::Real if deterministic => {
+                ClockPolicy::Virtual(UNIX_EPOCH + Duration::from_secs(DETERMINISTIC_START_SECS))
+            }
+            policy => policy,
+        };
+        let replay = self
+            .replay_host_calls
+            .as_deref()
+            .map(HostCallReplay::load)
+            .transpose()?;
+        let host_calls = HostCalls::new(
+            deterministic || replay.is_some(),
+            self.record_host_calls,
+            replay,
+        );
+        let factory = SandboxFactory::new(
+            guest_binary,
+            self.cfg,
+            self.host_print_fn,
+            clock_policy,
+            deterministic,
+            host_calls,
+        );
+
+        ProtoPySandbox::new(factory, self.runtime_cfg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sandbox::{PyValue, SandboxBuilder};
+
+    /// Recurse until the runtime stops the script, and return the error it raised
+    fn recursion_error(builder: SandboxBuilder) -> PyValue {
+        let sandbox = builder.build().unwrap().load_runtime().unwrap();
+        let mut sandbox = sandbox.get_loaded_sandbox().unwrap();
+        let code = "def f(n):\n    return f(n + 1)\n\ntry:\n    f(0)\n    error = None\nexcept RuntimeError as e:\n    error = str(e)".to_string();
+
+        // A guest fault would fail the call rather than raise into the script
+        assert!(sandbox.run_script(code).unwrap());
+        let error = sandbox.eval("error").unwrap();
+
+        // The sandbox keeps running scripts afterwards
+        assert!(sandbox.run_script("f = None".to_string()).unwrap());
+
+        error
+    }
+
+    #[test]
+    fn deep_recursion_raises_runtime_error() {
+        let expected = PyValue::Str("maximum recursion depth exceeded".to_string());
+
+        assert_eq!(recursion_error(SandboxBuilder::new()), expected);
+        assert_eq!(
+            recursion_error(SandboxBuilder::new().with_stack_size(256 * 1024)),
+            expected
+        );
+    }
+
+    #[test]
+    fn batch_rejects_recording_host_calls() {
+        let builder = SandboxBuilder::new().record_host_calls("host_calls.jsonl");
+        let scripts = vec!["print(1)".to_string(), "print(2)".to_string()];
+
+        let err = builder.run_batch(scripts, 2).unwrap_err();
+        assert!(err.to_string().contains("host_calls.jsonl"), "{}", err);
+    }
+
+    #[test]
+    fn deterministic_runs_keep_globals_and_record_evals() {
+        let sandbox = SandboxBuilder::new()
+            .deterministic(true)
+            .build()
+            .unwrap()
+            .load_runtime()
+            .unwrap();
+        let mut sandbox = sandbox.get_loaded_sandbox().unwrap();
+
+        let code = "import random, time\nfirst = random.getrandbits(32)".to_string();
+        assert!(sandbox.run_script(code).unwrap());
+        let first = sandbox.eval("first").unwrap();
+
+        // Reseeded, without dropping the globals of the previous run
+        let code = "second = random.getrandbits(32)".to_string();
+        assert!(sandbox.run_script(code).unwrap());
+        assert_eq!(sandbox.eval("second").unwrap(), first);
+        assert_eq!(sandbox.eval("first").unwrap(), first);
+
+        sandbox.eval("time.time_ns()").unwrap();
+        let functions: Vec<_> = sandbox
+            .host_calls()
+            .into_iter()
+            .map(|call| call.function)
+            .collect();
+        assert_eq!(functions, ["HostTimeNs"]);
+    }
+
+    #[test]
+    fn restore_each_run_drops_the_globals_of_previous_runs() {
+        let sandbox = SandboxBuilder::new()
+            .restore_each_run(true)
+            .build()
+            .unwrap()
+            .load_runtime()
+            .unwrap();
+        let mut sandbox = sandbox.get_loaded_sandbox().unwrap();
+
+        assert!(sandbox.run_script("answer = 42".to_string()).unwrap());
+        assert_eq!(sandbox.eval("answer").unwrap(), PyValue::Int(42));
 
-        ProtoPySandbox::new(guest_binary, Some(self.cfg), self.host_print_fn)
+        let code = "seen = 'answer' in globals()".to_string();
+        assert!(sandbox.run_script(code).unwrap());
+        assert_eq!(sandbox.eval("seen").unwrap(), PyValue::Bool(false));
     }
 }
@@ -0,0 +1,217 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::Write;
+use std::sync::Arc;
+
+use hyperlight_host::sandbox::SandboxConfiguration;
+use hyperlight_host::{GuestBinary, Result, UninitializedSandbox, new_error};
+use serde_json::{Value, json};
+
+use crate::HostPrintFn;
+use crate::sandbox::ClockPolicy;
+use crate::sandbox::clock::Clock;
+use crate::sandbox::exec_stats::HostCallTimer;
+use crate::sandbox::host_calls::HostCalls;
+
+/// Seed of the `random` module of deterministic sandboxes
+pub(crate) const DETERMINISTIC_SEED: u64 = 0x5eed;
+
+/// Guest binary running the Python runtime, kept for as long as sandboxes are created
+/// from it, see [`GuestBinary`]
+#[derive(Debug, Clone)]
+pub(crate) enum GuestImage {
+    /// The binary in memory, e.g. the embedded python-host
+    Buffer(&'static [u8]),
+    /// Path to the binary, read once by [`GuestImage::load`]
+    FilePath(String),
+    /// The binary read from its path, shared by the sandboxes created from it
+    File(Arc<[u8]>),
+}
+
+impl From<GuestBinary<'static>> for GuestImage {
+    fn from(binary: GuestBinary<'static>) -> Self {
+        match binary {
+            GuestBinary::Buffer(buffer) => GuestImage::Buffer(buffer),
+            GuestBinary::FilePath(path) => GuestImage::FilePath(path),
+        }
+    }
+}
+
+impl GuestImage {
+    /// Read the binary from its path, so that rebuilding a sandbox doesn't read it again
+    /// # Errors
+    /// Returns an error if the file could not be read
+    pub(crate) fn load(self) -> Result<Self> {
+        match self {
+            GuestImage::FilePath(path) => std::fs::read(&path)
+                .map(|bytes| GuestImage::File(bytes.into()))
+                .map_err(|e| new_error!("Could not read the guest binary {}: {}", path, e)),
+            image => Ok(image),
+        }
+    }
+
+    /// The binary for creating a sandbox
+    fn binary(&self) -> GuestBinary<'_> {
+        match self {
+            GuestImage::Buffer(buffer) => GuestBinary::Buffer(buffer),
+            GuestImage::FilePath(path) => GuestBinary::FilePath(path.clone()),
+            GuestImage::File(bytes) => GuestBinary::Buffer(bytes),
+        }
+    }
+}
+
+/// Creates the uninitialized sandboxes hosting the Python runtime.
+/// Kept by every sandbox state so that a sandbox can be rebuilt from scratch
+/// with the exact same configuration and host functions.
+#[derive(Clone)]
+pub(crate) struct SandboxFactory {
+    /// The guest binary running the Python runtime
+    guest_binary: GuestImage,
+    /// Configuration for the inner sandbox
+    cfg: SandboxConfiguration,
+    /// Optional host print function
+    host_print_fn: Option<Arc<HostPrintFn>>,
+    /// Time spent in host functions called by the guest
+    host_timer: HostCallTimer,
+    /// Clock read by the `time` module
+    clock: Clock,
+    /// Whether the host functions answer the same on every run
+    deterministic: bool,
+    /// Host calls made by the guest during the current run
+    host_calls: HostCalls,
+}
+
+impl SandboxFactory {
+    /// Create a new [`SandboxFactory`]
+    /// # Arguments
+    /// * `guest_binary` - The guest binary to use for the sandboxes
+    /// * `cfg` - Configuration for the sandboxes
+    /// * `host_print_fn` - Optional host print function
+    /// * `clock_policy` - Clock read by the `time` module
+    /// * `deterministic` - Whether the `random` module gets a fixed seed
+    /// * `host_calls` - Recording and replay of the host calls made by the guest
+    pub(crate) fn new(
+        guest_binary: GuestImage,
+        cfg: SandboxConfiguration,
+        host_print_fn: Option<HostPrintFn>,
+        clock_policy: ClockPolicy,
+        deterministic: bool,
+        host_calls: HostCalls,
+    ) -> Self {
+        Self {
+            guest_binary,
+            cfg,
+            host_print_fn: host_print_fn.map(Arc::new),
+            host_timer: HostCallTimer::default(),
+            clock: Clock::new(clock_policy),
+            deterministic,
+            host_calls,
+        }
+    }
+
+    /// Timer for the host functions called by the guest of the created sandboxes
+    pub(crate) fn host_timer(&self) -> &HostCallTimer {
+        &self.host_timer
+    }
+
+    /// Clock read by the `time` module of the created sandboxes
+    pub(crate) fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    /// Host calls made by the guest of the created sandboxes
+    pub(crate) fn host_calls(&self) -> &HostCalls {
+        &self.host_calls
+    }
+
+    /// Create a new uninitialized sandbox with the host functions registered
+    /// # Errors
+    /// Returns an error if the sandbox could not be created
+    pub(crate) fn create(&self) -> Result<UninitializedSandbox> {
+        let mut usbox = UninitializedSandbox::new(self.guest_binary.binary(), Some(self.cfg))?;
+
+        // Registered even without a host print function, so that the output is recorded
+        let host_print_fn = self.host_print_fn.clone();
+        let (timer, host_calls) = (self.host_timer.clone(), self.host_calls.clone());
+        usbox.register_print(move |msg: String| {
+            let len = host_calls.call("HostPrint", vec![json!(msg)], || {
+                Ok(json!(timer.time(|| match &host_print_fn {
+                    Some(host_print_fn) => host_print_fn.call((msg.clone(),)),
+                    None => print_stdout(&msg),
+                })?))
+            })?;
+            response(len, Value::as_i64).map(|len| len as i32)
+        })?;
+
+        let (clock, timer, host_calls) = self.clock_parts();
+        usbox.register("HostTimeNs", move || {
+            let ns = host_calls.call("HostTimeNs", vec![], || {
+                Ok(json!(timer.time(|| clock.time_ns())))
+            })?;
+            response(ns, Value::as_u64)
+        })?;
+
+        let (clock, timer, host_calls) = self.clock_parts();
+        usbox.register("HostTicksNs", move || {
+            let ns = host_calls.call("HostTicksNs", vec![], || {
+                Ok(json!(timer.time(|| clock.ticks_ns())))
+            })?;
+            response(ns, Value::as_u64)
+        })?;
+
+        let (clock, timer, host_calls) = self.clock_parts();
+        usbox.register("HostSleepNs", move |ns: u64| {
+            host_calls.call("HostSleepNs", vec![json!(ns)], || {
+                timer.time(|| clock.sleep_ns(ns));
+                Ok(Value::Null)
+            })?;
+            Ok(())
+        })?;
+
+        let (timer, host_calls) = (self.host_timer.clone(), self.host_calls.clone());
+        let deterministic = self.deterministic;
+        usbox.register("HostRandomSeed", move || {
+            let seed = host_calls.call("HostRandomSeed", vec![], || {
+                Ok(json!(if deterministic {
+                    DETERMINISTIC_SEED
+                } else {
+                    timer.time(random_seed)
+                }))
+            })?;
+            response(seed, Value::as_u64)
+        })?;
+
+        Ok(usbox)
+    }
+
+    /// What the clock host functions capture
+    fn clock_parts(&self) -> (Clock, HostCallTimer, HostCalls) {
+        (
+            self.clock.clone(),
+            self.host_timer.clone(),
+            self.host_calls.clone(),
+        )
+    }
+}
+
+/// Print to the host stdout, when no host print function is set
+fn print_stdout(msg: &str) -> Result<i32> {
+    let mut stdout = std::io::stdout().lock();
+    stdout
+        .write_all(msg.as_bytes())
+        .and_then(|()| stdout.flush())
+        .map_err(|e| new_error!("Could not print to stdout: {}", e))?;
+
+    Ok(msg.len() as i32)
+}
+
+/// Typed response of a host function, which may come from a recording
+fn response<T>(value: Value, as_type: impl FnOnce(&Value) -> Option<T>) -> Result<T> {
+    as_type(&value).ok_or_else(|| new_error!("Invalid recorded host call response: {}", value))
+}
+
+/// Seed of the `random` module drawn from the randomly keyed hasher of the standard library.
+/// Good enough for simulations, not for cryptography.
+fn random_seed() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
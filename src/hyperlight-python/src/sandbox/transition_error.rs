@@ -0,0 +1,157 @@
+use std::fmt;
+
+use hyperlight_host::{HyperlightError, new_error};
+
+/// Error returned by a failed sandbox state transition, such as
+/// [`ProtoPySandbox::load_runtime`](crate::sandbox::ProtoPySandbox::load_runtime),
+/// [`PySandbox::get_loaded_sandbox`](crate::sandbox::PySandbox::get_loaded_sandbox) or
+/// [`LoadedPySandbox::unload`](crate::sandbox::LoadedPySandbox::unload).
+/// Carries the original sandbox back so it can be inspected or the transition retried.
+///
+/// Displays as the error that made the transition fail, followed by the error of
+/// rebuilding the sandbox if that failed too.
+/// Converts into a [`HyperlightError`], so `?` keeps working in functions
+/// returning a [`hyperlight_host::Result`]: the error itself, or a message carrying
+/// both errors if rebuilding the sandbox failed too.
+///
+/// # Example
+/// ```
+/// use hyperlight_python::sandbox::SandboxBuilder;
+///
+/// fn main() -> hyperlight_host::Result<()> {
+///     let proto_sbox = SandboxBuilder::new()
+///         .build()?;
+///
+///     let sandbox = proto_sbox.load_runtime()?;
+///     let sandbox = match sandbox.get_loaded_sandbox() {
+///         Ok(sandbox) => sandbox,
+///         Err(e) => {
+///             eprintln!("Retrying after error: {}", e.error());
+///             e.into_sandbox().get_loaded_sandbox()?
+///         }
+///     };
+///     Ok(())
+/// }
+/// ```
+pub struct TransitionError<S> {
+    /// The sandbox the transition was started from
+    sandbox: S,
+    /// The error that made the transition fail
+    error: HyperlightError,
+    /// The error of creating a new inner sandbox after the failed transition, if any
+    rebuild_error: Option<HyperlightError>,
+}
+
+impl<S> TransitionError<S> {
+    /// Create a new [`TransitionError`]
+    /// # Arguments
+    /// * `sandbox` - The sandbox the transition was started from
+    /// * `error` - The error that made the transition fail
+    pub(crate) fn new(sandbox: S, error: HyperlightError) -> Self {
+        Self {
+            sandbox,
+            error,
+            rebuild_error: None,
+        }
+    }
+
+    /// Attach the error of creating a new inner sandbox after the failed transition
+    /// # Arguments
+    /// * `rebuild_error` - The error of creating the new inner sandbox
+    pub(crate) fn with_rebuild_error(mut self, rebuild_error: HyperlightError) -> Self {
+        self.rebuild_error = Some(rebuild_error);
+
+        self
+    }
+
+    /// The error that made the transition fail
+    pub fn error(&self) -> &HyperlightError {
+        &self.error
+    }
+
+    /// The error of creating a new inner sandbox after the failed transition, if that
+    /// failed too. The carried back sandbox can't be used for a retry in that case.
+    pub fn rebuild_error(&self) -> Option<&HyperlightError> {
+        self.rebuild_error.as_ref()
+    }
+
+    /// The sandbox the transition was started from
+    pub fn sandbox(&self) -> &S {
+        &self.sandbox
+    }
+
+    /// Take back the sandbox the transition was started from
+    pub fn into_sandbox(self) -> S {
+        self.sandbox
+    }
+
+    /// Split the error into the original sandbox, the error that made the transition
+    /// fail and the error of rebuilding the sandbox, if any
+    pub fn into_parts(self) -> (S, HyperlightError, Option<HyperlightError>) {
+        (self.sandbox, self.error, self.rebuild_error)
+    }
+}
+
+impl<S> fmt::Debug for TransitionError<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransitionError")
+            .field("error", &self.error)
+            .field("rebuild_error", &self.rebuild_error)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> fmt::Display for TransitionError<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.error, f)?;
+
+        match &self.rebuild_error {
+            Some(rebuild_error) => {
+                write!(f, "; the sandbox could not be rebuilt: {}", rebuild_error)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl<S> std::error::Error for TransitionError<S> {
+    // Displayed as the error itself, so the chain continues with its source
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+impl<S> From<TransitionError<S>> for HyperlightError {
+    fn from(e: TransitionError<S>) -> Self {
+        match e.rebuild_error {
+            Some(_) => new_error!("{}", e),
+            None => e.error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversion_keeps_the_rebuild_error() {
+        let e = TransitionError::new((), new_error!("load failed"));
+        assert_eq!(
+            HyperlightError::from(e).to_string(),
+            new_error!("load failed").to_string()
+        );
+
+        let e = TransitionError::new((), new_error!("load failed"))
+            .with_rebuild_error(new_error!("out of memory"));
+        let message = HyperlightError::from(e).to_string();
+        assert!(message.contains("load failed"), "{}", message);
+        assert!(message.contains("out of memory"), "{}", message);
+
+        let (_, error, rebuild_error) = TransitionError::new((), new_error!("load failed"))
+            .with_rebuild_error(new_error!("out of memory"))
+            .into_parts();
+        assert!(error.to_string().contains("load failed"));
+        assert!(rebuild_error.unwrap().to_string().contains("out of memory"));
+    }
+}
@@ -1,30 +1,157 @@
 //! Build script for embedding MicroPython runtime
 //!
 //! This script:
-//! 1. Clones the MicroPython repository (if not already present)
-//! 2. Checks out the specified version tag
-//! 3. Runs the embed build to generate micropython_embed package
+//! 1. Gets the MicroPython sources of the specified version tag: the tree named by
+//!    `MICROPYTHON_SRC_DIR`, the vendored tarball, or a clone of the repository
+//!    (if not already present) checked out at the tag
+//! 2. Runs the embed build to generate micropython_embed package for the
+//!    feature profile selected by the `profile-*` cargo features
+//! 3. Freezes the Python modules listed by the `HYPERLIGHT_PYTHON_FROZEN` manifest, if any
 //! 4. Compiles the C sources for bare metal x86
 //! 5. Generates Rust FFI bindings using bindgen
+//!
+//! Only the clone accesses the network.
 
 use std::env;
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
+use sha2::{Digest, Sha256};
+
 /// MicroPython version to use
 const MICROPYTHON_VERSION: &str = "v1.27.0";
 
+/// SHA-256 of the GitHub archive of `MICROPYTHON_VERSION`, which a vendored tarball must
+/// match. Updated along with the version; while empty, a vendored tarball is rejected
+/// with its actual hash so that it can be checked and pinned here.
+const MICROPYTHON_TARBALL_SHA256: &str = "";
+
 /// MicroPython repository URL
 const MICROPYTHON_REPO: &str = "https://github.com/micropython/micropython.git";
 
+/// Environment variable naming a MicroPython source tree, checked out at
+/// `MICROPYTHON_VERSION`, used in place of the clone
+const SRC_DIR_ENV: &str = "MICROPYTHON_SRC_DIR";
+
+/// Directory of the vendored source tarball, relative to the crate directory.
+/// The tarball is `micropython-<version>.tar.gz`, the GitHub archive of the tag.
+const VENDOR_DIR: &str = "vendor";
+
+/// File recording the hash of the tarball the sources were extracted from
+const VENDORED_STAMP: &str = ".vendored-sha256";
+
+/// Double precision libm routines from `lib/libm_dbl` needed by `MICROPY_FLOAT_IMPL_DOUBLE`,
+/// the `math` and `cmath` modules (same list as the bare metal ports)
+const LIBM_DBL_SOURCES: &[&str] = &[
+    "__cos.c",
+    "__expo2.c",
+    "__fpclassify.c",
+    "__rem_pio2.c",
+    "__rem_pio2_large.c",
+    "__signbit.c",
+    "__sin.c",
+    "__tan.c",
+    "acos.c",
+    "acosh.c",
+    "asin.c",
+    "asinh.c",
+    "atan.c",
+    "atan2.c",
+    "atanh.c",
+    "ceil.c",
+    "copysign.c",
+    "cos.c",
+    "cosh.c",
+    "erf.c",
+    "exp.c",
+    "expm1.c",
+    "floor.c",
+    "fmod.c",
+    "frexp.c",
+    "ldexp.c",
+    "lgamma.c",
+    "log.c",
+    "log10.c",
+    "log1p.c",
+    "modf.c",
+    "nearbyint.c",
+    "pow.c",
+    "rint.c",
+    "round.c",
+    "scalbn.c",
+    "sin.c",
+    "sinh.c",
+    "sqrt.c",
+    "tan.c",
+    "tanh.c",
+    "tgamma.c",
+    "trunc.c",
+];
+
+/// Feature profiles selected by the `profile-*` cargo features, from the smallest to the largest.
+/// The value is passed as `HL_PROFILE` to `mpconfigport.h`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Profile {
+    /// `MICROPY_CONFIG_ROM_LEVEL_MINIMUM`, the default
+    Minimal = 0,
+    /// `MICROPY_CONFIG_ROM_LEVEL_CORE_FEATURES`
+    Core = 1,
+    /// `MICROPY_CONFIG_ROM_LEVEL_EXTRA_FEATURES`
+    Extra = 2,
+    /// `MICROPY_CONFIG_ROM_LEVEL_FULL_FEATURES`
+    Full = 3,
+}
+
+impl Profile {
+    /// The largest profile whose cargo feature is enabled
+    fn from_features() -> Self {
+        [
+            ("CARGO_FEATURE_PROFILE_FULL", Profile::Full),
+            ("CARGO_FEATURE_PROFILE_EXTRA", Profile::Extra),
+            ("CARGO_FEATURE_PROFILE_CORE", Profile::Core),
+        ]
+        .into_iter()
+        .find(|(feature, _)| env::var_os(feature).is_some())
+        .map_or(Profile::Minimal, |(_, profile)| profile)
+    }
+
+    /// The `HL_PROFILE` define
+    fn define(self) -> String {
+        format!("-DHL_PROFILE={}", self as u8)
+    }
+
+    /// The cargo feature selecting the profile
+    fn feature(self) -> &'static str {
+        match self {
+            Profile::Minimal => "profile-minimal",
+            Profile::Core => "profile-core",
+            Profile::Extra => "profile-extra",
+            Profile::Full => "profile-full",
+        }
+    }
+}
+
+/// Environment variable naming the manifest of the Python modules frozen into the runtime
+const FROZEN_MANIFEST_ENV: &str = "HYPERLIGHT_PYTHON_FROZEN";
+
+/// Modules from `extmod` compiled from the profile on, must match `mpconfigport.h`
+const EXTMOD_MODULES: &[(&str, Profile)] = &[
+    ("modjson.c", Profile::Minimal),
+    ("modre.c", Profile::Minimal),
+    ("modtime.c", Profile::Minimal),
+    ("modrandom.c", Profile::Minimal),
+    ("modheapq.c", Profile::Core),
+    ("modbinascii.c", Profile::Extra),
+    ("moderrno.c", Profile::Extra),
+    ("modplatform.c", Profile::Extra),
+];
+
 fn main() {
     let out_dir = PathBuf::from(env::var("OUT_DIR").expect("OUT_DIR not set"));
     let manifest_dir =
         PathBuf::from(env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR not set"));
 
-    // Directory where we'll clone MicroPython
-    let micropython_dir = out_dir.join("micropython");
-
     // Directory where the embed package will be generated
     let embed_dir = out_dir.join("micropython_embed");
 
@@ -35,34 +162,186 @@ fn main() {
     println!("cargo:rerun-if-changed=stubs/include/mpconfigport.h");
     println!("cargo:rerun-if-changed=stubs/micropython_stubs.c");
     println!("cargo:rerun-if-changed=stubs/include/wrapper.h");
+    println!("cargo:rerun-if-changed=stubs/include/hyperlight_stubs.h");
+    println!("cargo:rerun-if-changed=stubs/re1.5/lib/re1.5/recursiveloop.c");
     println!("cargo:rerun-if-changed=Cargo.toml");
     println!("cargo:rerun-if-changed=build.rs");
 
-    // Clone MicroPython repository if not present
-    clone_micropython(&micropython_dir);
+    let profile = Profile::from_features();
+    println!(
+        "cargo:warning=Building MicroPython with the {:?} profile",
+        profile
+    );
+
+    let frozen_sources = frozen_sources();
+    let defines = [
+        profile.define(),
+        format!("-DHL_FROZEN={}", u8::from(!frozen_sources.is_empty())),
+    ];
+
+    // Features of the runtime, see `micropython_lib::FEATURES`
+    let mut features = vec![profile.feature()];
+    if !frozen_sources.is_empty() {
+        features.push("frozen");
+    }
+    println!(
+        "cargo:rustc-env=MICROPYTHON_FEATURES={}",
+        features.join(",")
+    );
 
-    // Checkout the correct version
-    checkout_version(&micropython_dir, MICROPYTHON_VERSION);
+    // Get the MicroPython sources, cloning the repository if they are not available locally
+    let micropython_dir = micropython_sources(&manifest_dir, &out_dir);
 
     // Build the embed package
-    build_embed_package(&micropython_dir, &embed_dir, &stubs_include_dir);
+    build_embed_package(&micropython_dir, &embed_dir, &stubs_include_dir, &defines);
 
     println!(
         "cargo:warning=MicroPython embed package built successfully at {:?}",
         embed_dir
     );
 
+    // Freeze the Python modules of the manifest as bytecode
+    let frozen_content = (!frozen_sources.is_empty()).then(|| {
+        freeze_modules(
+            &micropython_dir,
+            &out_dir.join("micropython_build"),
+            &frozen_sources,
+        )
+    });
+
     // Compile C sources for bare metal x86
-    compile_micropython(&embed_dir, &stubs_dir, &stubs_include_dir);
+    compile_micropython(
+        &micropython_dir,
+        &embed_dir,
+        &stubs_dir,
+        &stubs_include_dir,
+        profile,
+        frozen_content.as_deref(),
+    );
 
     println!("cargo:warning=MicroPython compiled successfully!");
 
     // Generate Rust FFI bindings
-    generate_bindings(&embed_dir, &stubs_include_dir, &out_dir);
+    generate_bindings(&embed_dir, &stubs_include_dir, &out_dir, &defines);
 
     println!("cargo:warning=Bindings generated successfully!");
 }
 
+/// Directory of the MicroPython sources: the tree named by `MICROPYTHON_SRC_DIR`,
+/// the vendored tarball extracted to `OUT_DIR/micropython` after checking its hash,
+/// or the repository cloned there and checked out at `MICROPYTHON_VERSION`
+fn micropython_sources(manifest_dir: &Path, out_dir: &Path) -> PathBuf {
+    println!("cargo:rerun-if-env-changed={}", SRC_DIR_ENV);
+
+    if let Some(src_dir) = env::var_os(SRC_DIR_ENV).map(PathBuf::from) {
+        if !src_dir.join("py").join("mpconfig.h").exists() {
+            panic!(
+                "{} is not a MicroPython source tree: {:?}",
+                SRC_DIR_ENV, src_dir
+            );
+        }
+
+        println!(
+            "cargo:warning=Using the MicroPython sources at {:?}",
+            src_dir
+        );
+        return src_dir;
+    }
+
+    // Directory where we'll extract or clone MicroPython
+    let micropython_dir = out_dir.join("micropython");
+
+    let tarball = manifest_dir
+        .join(VENDOR_DIR)
+        .join(format!("micropython-{}.tar.gz", MICROPYTHON_VERSION));
+    println!("cargo:rerun-if-changed={}", tarball.display());
+
+    if tarball.exists() {
+        extract_vendored(&tarball, &micropython_dir);
+    } else {
+        // Sources extracted from a tarball that was since removed
+        if micropython_dir.join(VENDORED_STAMP).exists() {
+            fs::remove_dir_all(&micropython_dir).expect("Failed to remove the MicroPython sources");
+        }
+
+        // Clone MicroPython repository if not present
+        clone_micropython(&micropython_dir);
+
+        // Checkout the correct version
+        checkout_version(&micropython_dir, MICROPYTHON_VERSION);
+    }
+
+    micropython_dir
+}
+
+/// Extract the vendored source tarball, unless the same tarball was already extracted.
+/// The tarball must match `MICROPYTHON_TARBALL_SHA256`.
+fn extract_vendored(tarball: &Path, target_dir: &Path) {
+    let data = fs::read(tarball).expect("Failed to read the vendored MicroPython sources");
+    let actual: String = Sha256::digest(&data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    if MICROPYTHON_TARBALL_SHA256.is_empty() {
+        panic!(
+            "No SHA-256 is pinned for the vendored MicroPython sources {:?}: check that {} is the hash of the {} archive and set it as MICROPYTHON_TARBALL_SHA256",
+            tarball, actual, MICROPYTHON_VERSION
+        );
+    }
+    if actual != MICROPYTHON_TARBALL_SHA256 {
+        panic!(
+            "The vendored MicroPython sources {:?} don't match the pinned SHA-256: expected {}, got {}",
+            tarball, MICROPYTHON_TARBALL_SHA256, actual
+        );
+    }
+
+    // The hash of the extracted tarball, to extract it again only when it changes
+    let stamp = target_dir.join(VENDORED_STAMP);
+    if fs::read_to_string(&stamp).is_ok_and(|extracted| extracted == actual) {
+        println!(
+            "cargo:warning=Vendored MicroPython sources already extracted at {:?}",
+            target_dir
+        );
+        return;
+    }
+
+    println!("cargo:warning=Extracting the vendored MicroPython sources...");
+
+    if target_dir.exists() {
+        fs::remove_dir_all(target_dir).expect("Failed to remove the MicroPython sources");
+    }
+    fs::create_dir_all(target_dir).expect("Failed to create the MicroPython directory");
+
+    // The archive of a tag has a single top-level directory, micropython-<version>
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(data.as_slice()));
+    for entry in archive
+        .entries()
+        .expect("Failed to read the vendored MicroPython sources")
+    {
+        let mut entry = entry.expect("Failed to read the vendored MicroPython sources");
+        let path = entry
+            .path()
+            .expect("Invalid path in the vendored MicroPython sources")
+            .components()
+            .skip(1)
+            .collect::<PathBuf>();
+        if path.as_os_str().is_empty() {
+            continue;
+        }
+
+        let dest = target_dir.join(path);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent).expect("Failed to create the MicroPython directory");
+        }
+        entry
+            .unpack(&dest)
+            .expect("Failed to extract the vendored MicroPython sources");
+    }
+
+    fs::write(&stamp, &actual).expect("Failed to write the vendored sources stamp");
+}
+
 /// Clone the MicroPython repository if it doesn't exist
 fn clone_micropython(target_dir: &Path) {
     if target_dir.join(".git").exists() {
@@ -126,7 +405,12 @@ fn checkout_version(repo_dir: &Path, version: &str) {
 }
 
 /// Build the MicroPython embed package
-fn build_embed_package(micropython_dir: &Path, embed_dir: &Path, config_include_dir: &Path) {
+fn build_embed_package(
+    micropython_dir: &Path,
+    embed_dir: &Path,
+    config_include_dir: &Path,
+    defines: &[String],
+) {
     println!("cargo:warning=Building MicroPython embed package...");
 
     // First, let's create a build directory
@@ -156,7 +440,10 @@ include $(MICROPYTHON_TOP)/ports/embed/embed.mk
     let status = Command::new("make")
         .args(["-f", "micropython_embed.mk"])
         .current_dir(&build_dir)
-        .env("CFLAGS", format!("-I{}", config_include_dir.display()))
+        .env(
+            "CFLAGS",
+            format!("-I{} {}", config_include_dir.display(), defines.join(" ")),
+        )
         .status()
         .expect("Failed to execute make");
 
@@ -179,13 +466,25 @@ include $(MICROPYTHON_TOP)/ports/embed/embed.mk
 }
 
 /// Compile MicroPython C sources for bare metal x86
-fn compile_micropython(embed_dir: &Path, stubs_dir: &Path, stubs_include_dir: &Path) {
+fn compile_micropython(
+    micropython_dir: &Path,
+    embed_dir: &Path,
+    stubs_dir: &Path,
+    stubs_include_dir: &Path,
+    profile: Profile,
+    frozen_content: Option<&Path>,
+) {
     println!("cargo:warning=Compiling MicroPython C sources...");
 
     let mut build = cc::Build::new();
 
     // Use gnu99 instead of c99 to support GNU extensions (inline asm, etc.)
     build.std("gnu99").flag("-fPIC").flag("-Wno-sign-compare");
+    build.define("HL_PROFILE", (profile as u8).to_string().as_str());
+    build.define(
+        "HL_FROZEN",
+        u8::from(frozen_content.is_some()).to_string().as_str(),
+    );
 
     // Include paths
     // Our mpconfigport.h
@@ -197,6 +496,10 @@ fn compile_micropython(embed_dir: &Path, stubs_dir: &Path, stubs_include_dir: &P
     build.include(embed_dir.join("exmod"));
     build.include(embed_dir.join("shared"));
     build.include(embed_dir.join("genhdr"));
+    // The re1.5 matcher with a backtracking limit, included by extmod/modre.c
+    // in place of the one in the MicroPython sources that come after it
+    build.include(stubs_dir.join("re1.5"));
+    build.include(micropython_dir);
 
     // Collect all C source files from the embed package
     let mut c_files: Vec<PathBuf> = Vec::new();
@@ -223,6 +526,23 @@ fn compile_micropython(embed_dir: &Path, stubs_dir: &Path, stubs_include_dir: &P
         build.file(c_file);
     }
 
+    // The guest has no libm, floats use MicroPython's double precision one
+    let libm_dir = micropython_dir.join("lib").join("libm_dbl");
+    for source in LIBM_DBL_SOURCES {
+        build.file(libm_dir.join(source));
+    }
+
+    // extmod modules enabled by the profile, the embed package only carries their headers
+    let extmod_dir = micropython_dir.join("extmod");
+    for (source, _) in EXTMOD_MODULES.iter().filter(|(_, from)| profile >= *from) {
+        build.file(extmod_dir.join(source));
+    }
+
+    // Bytecode of the frozen Python modules
+    if let Some(frozen_content) = frozen_content {
+        build.file(frozen_content);
+    }
+
     // Add our stubs
     let stubs_file = stubs_dir.join("micropython_stubs.c");
     if stubs_file.exists() {
@@ -236,6 +556,105 @@ fn compile_micropython(embed_dir: &Path, stubs_dir: &Path, stubs_include_dir: &P
     println!("cargo:warning=MicroPython library compiled successfully");
 }
 
+/// Python modules and packages to freeze into the runtime, listed one path per line by
+/// the manifest named by `HYPERLIGHT_PYTHON_FROZEN`. Relative paths are relative to the
+/// manifest, blank lines and lines starting with `#` are skipped.
+/// A `.py` file is frozen as a module, a directory as a package.
+fn frozen_sources() -> Vec<PathBuf> {
+    println!("cargo:rerun-if-env-changed={}", FROZEN_MANIFEST_ENV);
+
+    let Some(manifest) = env::var_os(FROZEN_MANIFEST_ENV).map(PathBuf::from) else {
+        return Vec::new();
+    };
+    println!("cargo:rerun-if-changed={}", manifest.display());
+
+    let text = fs::read_to_string(&manifest).unwrap_or_else(|e| {
+        panic!(
+            "Failed to read the frozen modules manifest {:?}: {}",
+            manifest, e
+        )
+    });
+    let base_dir = manifest.parent().unwrap_or(Path::new("."));
+
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let source = base_dir.join(line);
+            let is_module = source.is_file() && source.extension().is_some_and(|e| e == "py");
+            if !is_module && !source.is_dir() {
+                panic!(
+                    "Frozen module {:?} listed in {:?} is neither a .py file nor a package directory",
+                    source, manifest
+                );
+            }
+
+            println!("cargo:rerun-if-changed={}", source.display());
+            source
+        })
+        .collect()
+}
+
+/// Freeze Python modules and packages into `frozen_content.c` as bytecode,
+/// with MicroPython's `mpy-cross` compiler and `makemanifest.py` tool.
+/// Returns the path of `frozen_content.c`.
+fn freeze_modules(micropython_dir: &Path, build_dir: &Path, sources: &[PathBuf]) -> PathBuf {
+    println!(
+        "cargo:warning=Freezing {} Python modules and packages...",
+        sources.len()
+    );
+
+    // mpy-cross runs on the build machine, compiling the modules to bytecode.
+    // It is built under OUT_DIR, the sources may be a read-only MICROPYTHON_SRC_DIR.
+    let mpy_cross_dir = build_dir.join("mpy-cross");
+    let status = Command::new("make")
+        .arg("-C")
+        .arg(micropython_dir.join("mpy-cross"))
+        .arg(format!("BUILD={}", mpy_cross_dir.display()))
+        .status()
+        .expect("Failed to execute make");
+
+    if !status.success() {
+        panic!("Failed to build mpy-cross");
+    }
+
+    // A MicroPython manifest freezing every source from its parent directory
+    let manifest: String = sources
+        .iter()
+        .map(|source| {
+            let name = source.file_name().unwrap().to_string_lossy();
+            let base_path = source.parent().unwrap().to_string_lossy();
+            let kind = if source.is_dir() { "package" } else { "module" };
+            format!("{}({:?}, base_path={:?})\n", kind, name, base_path)
+        })
+        .collect();
+
+    let manifest_path = build_dir.join("frozen_manifest.py");
+    fs::write(&manifest_path, manifest).expect("Failed to write frozen_manifest.py");
+
+    // The qstrs of the runtime are read from the embed build, see embed.mk
+    let frozen_content = build_dir.join("frozen_content.c");
+    let status = Command::new("python3")
+        .env("MICROPY_MPYCROSS", mpy_cross_dir.join("mpy-cross"))
+        .arg(micropython_dir.join("tools").join("makemanifest.py"))
+        .arg("-o")
+        .arg(&frozen_content)
+        .arg("-b")
+        .arg(build_dir.join("build-embed"))
+        .arg("-v")
+        .arg(format!("MPY_DIR={}", micropython_dir.display()))
+        .arg("--mpy-tool-flags=-mlongint-impl=mpz")
+        .arg(&manifest_path)
+        .status()
+        .expect("Failed to execute makemanifest.py");
+
+    if !status.success() {
+        panic!("Failed to freeze the Python modules");
+    }
+
+    frozen_content
+}
+
 /// Collect all .c files in a directory (non-recursive)
 fn collect_c_files(dir: &Path, files: &mut Vec<PathBuf>) {
     if let Ok(entries) = std::fs::read_dir(dir) {
@@ -263,7 +682,12 @@ fn collect_c_files_recursive(dir: &Path, files: &mut Vec<PathBuf>) {
 }
 
 /// Generate Rust FFI bindings using bindgen
-fn generate_bindings(embed_dir: &Path, stubs_include_dir: &Path, out_dir: &Path) {
+fn generate_bindings(
+    embed_dir: &Path,
+    stubs_include_dir: &Path,
+    out_dir: &Path,
+    defines: &[String],
+) {
     println!("cargo:warning=Generating Rust FFI bindings...");
 
     // Create a wrapper header that includes the MicroPython embed API
@@ -288,6 +712,7 @@ fn generate_bindings(embed_dir: &Path, stubs_include_dir: &Path, out_dir: &Path)
         .clang_arg(format!("-I{}", stubs_include_dir.display()))
         .clang_arg(format!("-I{}", embed_dir.display()))
         .clang_arg(format!("-I{}", embed_dir.join("port").display()))
+        .clang_args(defines)
         // Use core instead of std
         .use_core()
         // Don't generate layout tests (they require std)
@@ -299,9 +724,36 @@ fn generate_bindings(embed_dir: &Path, stubs_include_dir: &Path, out_dir: &Path)
         .allowlist_function("mp_embed_init")
         .allowlist_function("mp_embed_deinit")
         .allowlist_function("mp_embed_exec_str")
+        .allowlist_function("hl_exec_str")
+        .allowlist_function("hl_eval_str")
+        .allowlist_function("hl_call_function")
+        .allowlist_function("hl_last_exception")
+        .allowlist_function("hl_set_global_json")
+        .allowlist_function("hl_eval_json")
+        .allowlist_function("hl_re_set_backtrack_limit")
+        .allowlist_function("hl_random_seed")
+        .allowlist_function("hl_set_import_call_policy")
+        .allowlist_var("HL_IMPORT_CALL_.*")
+        // Objects and native modules, see src/obj.rs and src/native.rs
+        .allowlist_function("hl_exception_new")
+        .allowlist_function("hl_obj_.*")
+        .allowlist_function("hl_native_fun_new")
+        .allowlist_function("hl_module_new")
+        .allowlist_function("hl_nlr_call")
+        .allowlist_function("hl_gc_.*")
+        .allowlist_type("hl_native_fun_t")
+        // Version of the MicroPython sources
+        .allowlist_var("MICROPY_VERSION_(MAJOR|MINOR|MICRO)")
+        // Heap statistics for the per-run execution stats
+        .allowlist_function("gc_info")
+        .allowlist_function("m_get_total_bytes_allocated")
+        // Recursion limit derived from the stack pointer at each guest function call
+        .allowlist_function("mp_stack_set_limit")
+        .allowlist_function("mp_stack_set_top")
         // Also allow some useful types
         .allowlist_type("mp_obj_t")
         .allowlist_type("mp_state_ctx_t")
+        .allowlist_type("gc_info_t")
         // Generate bindings
         .generate()
         .expect("Unable to generate bindings");
@@ -4,4 +4,17 @@
 #![allow(non_snake_case)]
 #![no_std]
 
+extern crate alloc;
+
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+
+/// Features the runtime was built with, comma-separated: the `profile-*` feature profile,
+/// and `frozen` if Python modules are frozen into it
+pub const FEATURES: &str = env!("MICROPYTHON_FEATURES");
+
+/// Python modules implemented in Rust
+pub mod native;
+/// Python objects of the runtime
+mod obj;
+
+pub use obj::{MpError, MpExcType, MpIter, MpObj, MpResult, MpRoot, gc_collect, nlr_protect};
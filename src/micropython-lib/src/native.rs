@@ -0,0 +1,215 @@
+//! Python modules implemented in Rust.
+//!
+//! A [`NativeModule`] is a set of functions taking their positional arguments as
+//! [`Args`] and returning an [`MpObj`], or an [`MpError`] raised as a Python exception.
+//! Registering the module creates it in the table of native modules, which `import`
+//! looks up like MicroPython's built-in module table. `python-host` registers its
+//! native modules, such as `fastmath`, in `native_modules.rs`.
+//!
+//! # Example
+//! The example needs a running runtime, so it isn't run as a doctest:
+//! ```ignore
+//! use micropython_lib::native::{Args, NativeModule};
+//! use micropython_lib::{MpError, MpObj, MpResult};
+//!
+//! /// clamp(value, low, high)
+//! fn clamp(args: Args) -> MpResult<MpObj> {
+//!     args.expect(3, 3)?;
+//!     let (value, low, high) = (args.int(0)?, args.int(1)?, args.int(2)?);
+//!     if low > high {
+//!         return Err(MpError::value_error("low is greater than high"));
+//!     }
+//!
+//!     MpObj::new_int(value.clamp(low, high))
+//! }
+//!
+//! NativeModule::new("fastmath").function("clamp", clamp).register()?;
+//! ```
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::vec::Vec;
+use core::ffi::{c_char, c_int, c_void};
+
+use crate::mp_obj_t;
+use crate::obj::{MpError, MpObj, MpResult, catch};
+
+/// A Python function implemented in Rust
+pub type NativeFn = fn(Args) -> MpResult<MpObj>;
+
+/// Positional arguments of a call to a [`NativeFn`]
+#[derive(Debug, Clone, Copy)]
+pub struct Args<'a> {
+    /// Name of the function called, for the error messages
+    name: &'static str,
+    /// The arguments
+    args: &'a [MpObj],
+}
+
+impl<'a> Args<'a> {
+    /// Number of arguments
+    pub fn len(&self) -> usize {
+        self.args.len()
+    }
+
+    /// Whether the function was called without arguments
+    pub fn is_empty(&self) -> bool {
+        self.args.is_empty()
+    }
+
+    /// The arguments
+    pub fn as_slice(&self) -> &'a [MpObj] {
+        self.args
+    }
+
+    /// Raise a `TypeError` unless there are between `min` and `max` arguments
+    pub fn expect(&self, min: usize, max: usize) -> MpResult<()> {
+        let count = self.args.len();
+        if (min..=max).contains(&count) {
+            return Ok(());
+        }
+
+        let expected = if min == max {
+            format!("{}", min)
+        } else {
+            format!("from {} to {}", min, max)
+        };
+        Err(MpError::type_error(format!(
+            "{}() takes {} positional arguments but {} were given",
+            self.name, expected, count
+        )))
+    }
+
+    /// The argument at `index`, if given
+    pub fn get(&self, index: usize) -> Option<MpObj> {
+        self.args.get(index).copied()
+    }
+
+    /// The argument at `index`, raising a `TypeError` if it is missing
+    pub fn arg(&self, index: usize) -> MpResult<MpObj> {
+        self.get(index).ok_or_else(|| self.missing(index))
+    }
+
+    /// The `bool` value of the argument at `index`
+    pub fn bool(&self, index: usize) -> MpResult<bool> {
+        self.arg(index)?.is_true()
+    }
+
+    /// The `int` argument at `index`
+    pub fn int(&self, index: usize) -> MpResult<i64> {
+        self.arg(index)?.as_int()
+    }
+
+    /// The `float` (or `int`) argument at `index`
+    pub fn float(&self, index: usize) -> MpResult<f64> {
+        self.arg(index)?.as_float()
+    }
+
+    /// The `str` argument at `index`, borrowed for the call
+    pub fn str(&self, index: usize) -> MpResult<&'a str> {
+        let arg = self.args.get(index).ok_or_else(|| self.missing(index))?;
+        // The caller keeps the arguments alive during the call and a `str` never changes
+        unsafe { arg.as_str() }
+    }
+
+    /// The `bytes` (or any buffer) argument at `index`, copied since a `bytearray`
+    /// could change if the function calls back into Python
+    pub fn bytes(&self, index: usize) -> MpResult<Vec<u8>> {
+        self.arg(index)?.try_into()
+    }
+
+    fn missing(&self, index: usize) -> MpError {
+        MpError::type_error(format!("{}() missing argument {}", self.name, index + 1))
+    }
+}
+
+/// A function of a [`NativeModule`], kept alive for as long as the runtime
+struct NativeFunction {
+    name: &'static str,
+    fun: NativeFn,
+}
+
+/// Called by the Python function objects of the native functions, see `hl_native_fun_t`
+unsafe extern "C" fn call_native(
+    data: *const c_void,
+    n_args: usize,
+    args: *const mp_obj_t,
+    result: *mut mp_obj_t,
+) -> c_int {
+    let function = unsafe { &*(data as *const NativeFunction) };
+    let args: &[MpObj] = match n_args {
+        0 => &[],
+        _ => unsafe { core::slice::from_raw_parts(args as *const MpObj, n_args) },
+    };
+
+    let (status, obj) = match (function.fun)(Args {
+        name: function.name,
+        args,
+    }) {
+        Ok(obj) => (0, obj),
+        Err(e) => (1, e.into_exception()),
+    };
+
+    unsafe {
+        *result = obj.as_raw();
+    }
+    status
+}
+
+/// A Python module implemented in Rust
+#[derive(Debug, Clone)]
+pub struct NativeModule {
+    /// Name the module is imported by
+    name: &'static str,
+    /// Functions of the module
+    functions: Vec<(&'static str, NativeFn)>,
+}
+
+impl NativeModule {
+    /// A new module without functions
+    /// # Arguments
+    /// * `name` - Name the module is imported by
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            functions: Vec::new(),
+        }
+    }
+
+    /// Add a function to the module
+    /// # Arguments
+    /// * `name` - Name of the function in the module
+    /// * `fun` - The function
+    pub fn function(mut self, name: &'static str, fun: NativeFn) -> Self {
+        self.functions.push((name, fun));
+
+        self
+    }
+
+    /// Name the module is imported by
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Create the module in the table of native modules, making it importable.
+    /// Must be called once the runtime is initialized, and once per runtime:
+    /// the functions are kept for as long as the guest runs.
+    pub fn register(&self) -> MpResult<()> {
+        let module = catch(|out| unsafe {
+            crate::hl_module_new(self.name.as_ptr() as *const c_char, self.name.len(), out)
+        })?;
+
+        for &(name, fun) in &self.functions {
+            let function = Box::leak(Box::new(NativeFunction { name, fun }));
+            let data = function as *const NativeFunction as *const c_void;
+            let (name_ptr, name_len) = (name.as_ptr() as *const c_char, name.len());
+
+            let fun_obj = catch(|out| unsafe {
+                crate::hl_native_fun_new(name_ptr, name_len, Some(call_native), data, out)
+            })?;
+            module.set_attr(name, fun_obj)?;
+        }
+
+        Ok(())
+    }
+}
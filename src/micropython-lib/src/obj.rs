@@ -0,0 +1,638 @@
+//! Python objects of the MicroPython runtime.
+//!
+//! Every operation goes through the helpers of `micropython_stubs.c`, which catch
+//! the exceptions MicroPython raises so that they never unwind through Rust frames:
+//! they come back as [`MpError::Raised`].
+//!
+//! The garbage collector only sees the objects reachable from Python or from the
+//! guest stack: an [`MpObj`] kept in a Rust heap allocation, e.g. a `Vec`, across
+//! calls that allocate must be rooted with an [`MpRoot`], or be an item of a
+//! rooted list. See [`MpObj`].
+//!
+//! Rust values convert to objects and back with `TryFrom`: `bool`, `i64`, `f64`,
+//! `str` and `String`, bytes, `Vec` to and from `list`, and `BTreeMap` to and from
+//! `dict`, nesting as deep as needed.
+//!
+//! # Example
+//! ```ignore
+//! use micropython_lib::{MpObj, MpResult};
+//!
+//! fn dumps(value: MpObj) -> MpResult<String> {
+//!     let json = MpObj::import("json")?;
+//!     let text = json.call_method("dumps", &[value])?;
+//!
+//!     text.try_into()
+//! }
+//! ```
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::Infallible;
+use core::ffi::{CStr, c_char, c_int, c_void};
+use core::fmt;
+use core::ptr;
+
+use crate::mp_obj_t;
+
+/// Result of an operation of the MicroPython runtime
+pub type MpResult<T> = Result<T, MpError>;
+
+/// Exception types Rust code can raise, must match `hl_exception_types` in `micropython_stubs.c`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MpExcType {
+    Exception,
+    TypeError,
+    ValueError,
+    RuntimeError,
+    KeyError,
+    IndexError,
+    AttributeError,
+    OverflowError,
+    ZeroDivisionError,
+    NotImplementedError,
+    OSError,
+    MemoryError,
+}
+
+/// Error of an operation of the MicroPython runtime.
+/// Displayed as `ExceptionType: message`.
+#[derive(Debug)]
+pub enum MpError {
+    /// The exception raised by the runtime
+    Raised(MpObj),
+    /// A new exception to raise, with its message
+    New(MpExcType, String),
+}
+
+impl MpError {
+    /// A new exception to raise
+    /// # Arguments
+    /// * `exc_type` - Type of the exception
+    /// * `msg` - Message of the exception
+    pub fn new(exc_type: MpExcType, msg: impl Into<String>) -> Self {
+        MpError::New(exc_type, msg.into())
+    }
+
+    /// A new `TypeError` to raise
+    pub fn type_error(msg: impl Into<String>) -> Self {
+        Self::new(MpExcType::TypeError, msg)
+    }
+
+    /// A new `ValueError` to raise
+    pub fn value_error(msg: impl Into<String>) -> Self {
+        Self::new(MpExcType::ValueError, msg)
+    }
+
+    /// A new `RuntimeError` to raise
+    pub fn runtime_error(msg: impl Into<String>) -> Self {
+        Self::new(MpExcType::RuntimeError, msg)
+    }
+
+    /// The exception object, created if needed.
+    /// If creating it fails, the exception raised doing so is returned instead.
+    pub fn into_exception(self) -> MpObj {
+        match self {
+            MpError::Raised(exc) => exc,
+            MpError::New(exc_type, msg) => {
+                let data = msg.as_ptr() as *const c_char;
+                MpObj(unsafe { crate::hl_exception_new(exc_type as usize, data, msg.len()) })
+            }
+        }
+    }
+}
+
+impl fmt::Display for MpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MpError::Raised(exc) => match exc.to_str() {
+                Ok(msg) if !msg.is_empty() => write!(f, "{}: {}", exc.type_name(), msg),
+                _ => write!(f, "{}", exc.type_name()),
+            },
+            MpError::New(exc_type, msg) => write!(f, "{:?}: {}", exc_type, msg),
+        }
+    }
+}
+
+/// Run a helper, turning its status into a result.
+/// `out` is the object it creates on success, the exception raised on failure.
+pub(crate) fn catch(helper: impl FnOnce(*mut mp_obj_t) -> c_int) -> MpResult<MpObj> {
+    let mut out: mp_obj_t = ptr::null_mut();
+
+    match helper(&mut out) {
+        0 => Ok(MpObj(out)),
+        _ => Err(MpError::Raised(MpObj(out))),
+    }
+}
+
+/// A Python object.
+///
+/// # Rooting
+/// An `MpObj` is a plain pointer into the GC heap: copying it doesn't keep the object
+/// alive. The object stays alive while it is reachable from Python, e.g. from a
+/// global, a rooted object or the arguments of a native function call, or while the
+/// `MpObj` is a local of a function running, since the collector scans the guest stack.
+/// Anything else, e.g. objects collected into a `Vec<MpObj>` or kept in a static,
+/// must be rooted with an [`MpRoot`] before the runtime allocates again; rooting a
+/// `list` of them takes a single slot:
+/// ```ignore
+/// let items: Vec<MpObj> = ...;
+/// let root = MpRoot::new(MpObj::try_from(items)?)?;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct MpObj(mp_obj_t);
+
+impl MpObj {
+    /// Wrap a raw object
+    /// # Safety
+    /// `obj` must be a valid object of the runtime
+    pub unsafe fn from_raw(obj: mp_obj_t) -> Self {
+        MpObj(obj)
+    }
+
+    /// The raw object
+    pub fn as_raw(self) -> mp_obj_t {
+        self.0
+    }
+
+    /// `None`
+    pub fn none() -> Self {
+        MpObj(unsafe { crate::hl_obj_none() })
+    }
+
+    /// `True` or `False`
+    pub fn new_bool(value: bool) -> Self {
+        MpObj(unsafe { crate::hl_obj_bool(value) })
+    }
+
+    /// An `int`
+    pub fn new_int(value: i64) -> MpResult<Self> {
+        catch(|out| unsafe { crate::hl_obj_new_int(value, out) })
+    }
+
+    /// A `float`
+    pub fn new_float(value: f64) -> MpResult<Self> {
+        catch(|out| unsafe { crate::hl_obj_new_float(value, out) })
+    }
+
+    /// A `str`
+    pub fn new_str(value: &str) -> MpResult<Self> {
+        catch(|out| unsafe {
+            crate::hl_obj_new_str(value.as_ptr() as *const c_char, value.len(), out)
+        })
+    }
+
+    /// A `bytes`
+    pub fn new_bytes(value: &[u8]) -> MpResult<Self> {
+        catch(|out| unsafe { crate::hl_obj_new_bytes(value.as_ptr(), value.len(), out) })
+    }
+
+    /// A `tuple` of the items
+    pub fn new_tuple(items: &[MpObj]) -> MpResult<Self> {
+        catch(|out| unsafe {
+            crate::hl_obj_new_tuple(items.as_ptr() as *const mp_obj_t, items.len(), out)
+        })
+    }
+
+    /// A `list` of the items
+    pub fn new_list(items: &[MpObj]) -> MpResult<Self> {
+        catch(|out| unsafe {
+            crate::hl_obj_new_list(items.as_ptr() as *const mp_obj_t, items.len(), out)
+        })
+    }
+
+    /// Whether the object is `None`
+    pub fn is_none(self) -> bool {
+        self == Self::none()
+    }
+
+    /// Name of the type of the object
+    pub fn type_name(self) -> &'static str {
+        unsafe { CStr::from_ptr(crate::hl_obj_type_name(self.0)) }
+            .to_str()
+            .unwrap_or("?")
+    }
+
+    /// Truth value of the object, as `bool(obj)`
+    pub fn is_true(self) -> MpResult<bool> {
+        let mut value = false;
+        catch(|exc| unsafe { crate::hl_obj_is_true(self.0, &mut value, exc) })?;
+
+        Ok(value)
+    }
+
+    /// Value of an `int` or `bool`, raising an `OverflowError` if it doesn't fit an `i64`
+    pub fn as_int(self) -> MpResult<i64> {
+        let mut value = 0;
+        catch(|exc| unsafe { crate::hl_obj_get_int(self.0, &mut value, exc) })?;
+
+        Ok(value)
+    }
+
+    /// Value of a `float`, `int` or `bool`
+    pub fn as_float(self) -> MpResult<f64> {
+        let mut value = 0.0;
+        catch(|exc| unsafe { crate::hl_obj_get_float(self.0, &mut value, exc) })?;
+
+        Ok(value)
+    }
+
+    /// Content of a `str`, borrowed from the object.
+    /// Copy it with [`String::try_from`] to keep it.
+    /// # Safety
+    /// The object must stay alive while the content is borrowed: rooted with an [`MpRoot`],
+    /// reachable from a rooted object, or not collected because nothing allocates meanwhile.
+    pub unsafe fn as_str(&self) -> MpResult<&str> {
+        let mut data: *const c_char = ptr::null();
+        let mut len = 0;
+        catch(|exc| unsafe { crate::hl_obj_get_str(self.0, &mut data, &mut len, exc) })?;
+
+        let bytes = unsafe { core::slice::from_raw_parts(data as *const u8, len) };
+        core::str::from_utf8(bytes).map_err(|_| MpError::value_error("invalid UTF-8 in str"))
+    }
+
+    /// Content of a `bytes`, `bytearray` or any object with the buffer protocol,
+    /// borrowed from the object. Copy it with [`Vec::try_from`] to keep it.
+    /// # Safety
+    /// The object must stay alive while the content is borrowed, as for [`MpObj::as_str`],
+    /// and a mutable buffer, e.g. a `bytearray`, must not change meanwhile.
+    pub unsafe fn as_bytes(&self) -> MpResult<&[u8]> {
+        let mut data: *const u8 = ptr::null();
+        let mut len = 0;
+        catch(|exc| unsafe { crate::hl_obj_get_buffer(self.0, &mut data, &mut len, exc) })?;
+
+        if len == 0 {
+            return Ok(&[]);
+        }
+        Ok(unsafe { core::slice::from_raw_parts(data, len) })
+    }
+
+    /// An empty `dict`
+    pub fn new_dict() -> MpResult<Self> {
+        catch(|out| unsafe { crate::hl_obj_new_dict(out) })
+    }
+
+    /// Import a module, as `__import__(name)`. The sandbox module policy applies.
+    /// For a dotted name, the top-level package is returned.
+    pub fn import(name: &str) -> MpResult<Self> {
+        catch(|out| unsafe {
+            crate::hl_obj_import(name.as_ptr() as *const c_char, name.len(), out)
+        })
+    }
+
+    /// `str(obj)`
+    pub fn to_str(self) -> MpResult<String> {
+        let text = catch(|out| unsafe { crate::hl_obj_str(self.0, false, out) })?;
+        String::try_from(text)
+    }
+
+    /// `repr(obj)`
+    pub fn repr(self) -> MpResult<String> {
+        let text = catch(|out| unsafe { crate::hl_obj_str(self.0, true, out) })?;
+        String::try_from(text)
+    }
+
+    /// `len(obj)`
+    pub fn len(self) -> MpResult<usize> {
+        let mut len = 0;
+        catch(|exc| unsafe { crate::hl_obj_len(self.0, &mut len, exc) })?;
+
+        Ok(len)
+    }
+
+    /// `len(obj) == 0`
+    pub fn is_empty(self) -> MpResult<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Items of a `list` or `tuple`, copied so that the list can change afterwards.
+    /// The items stay alive as long as the list or tuple does.
+    pub fn items(self) -> MpResult<Vec<MpObj>> {
+        let mut items: *mut mp_obj_t = ptr::null_mut();
+        let mut len = 0;
+        catch(|exc| unsafe { crate::hl_obj_get_array(self.0, &mut items, &mut len, exc) })?;
+
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+        Ok(unsafe { core::slice::from_raw_parts(items as *const MpObj, len) }.to_vec())
+    }
+
+    /// Append an item to a `list`
+    pub fn append(self, item: MpObj) -> MpResult<()> {
+        catch(|exc| unsafe { crate::hl_obj_list_append(self.0, item.0, exc) })?;
+
+        Ok(())
+    }
+
+    /// `obj[key]`
+    pub fn get_item(self, key: MpObj) -> MpResult<MpObj> {
+        catch(|out| unsafe { crate::hl_obj_get_item(self.0, key.0, out) })
+    }
+
+    /// `obj[key] = value`
+    pub fn set_item(self, key: MpObj, value: MpObj) -> MpResult<()> {
+        catch(|exc| unsafe { crate::hl_obj_set_item(self.0, key.0, value.0, exc) })?;
+
+        Ok(())
+    }
+
+    /// `obj.name`
+    pub fn attr(self, name: &str) -> MpResult<MpObj> {
+        catch(|out| unsafe {
+            crate::hl_obj_load_attr(self.0, name.as_ptr() as *const c_char, name.len(), out)
+        })
+    }
+
+    /// `obj.name = value`
+    pub fn set_attr(self, name: &str, value: MpObj) -> MpResult<()> {
+        catch(|exc| unsafe {
+            let name_ptr = name.as_ptr() as *const c_char;
+            crate::hl_obj_store_attr(self.0, name_ptr, name.len(), value.0, exc)
+        })?;
+
+        Ok(())
+    }
+
+    /// `obj(*args)`
+    pub fn call(self, args: &[MpObj]) -> MpResult<MpObj> {
+        catch(|out| unsafe {
+            crate::hl_obj_call(self.0, args.as_ptr() as *const mp_obj_t, args.len(), out)
+        })
+    }
+
+    /// `obj.name(*args)`
+    pub fn call_method(self, name: &str, args: &[MpObj]) -> MpResult<MpObj> {
+        self.attr(name)?.call(args)
+    }
+
+    /// `iter(obj)`
+    pub fn iter(self) -> MpResult<MpIter> {
+        let iter = catch(|out| unsafe { crate::hl_obj_getiter(self.0, out) })?;
+
+        Ok(MpIter(Some(iter)))
+    }
+}
+
+/// Iterator over a Python iterable, see [`MpObj::iter`].
+/// Stops after the first exception raised.
+#[derive(Debug)]
+pub struct MpIter(Option<MpObj>);
+
+impl Iterator for MpIter {
+    type Item = MpResult<MpObj>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let iter = self.0?;
+
+        match catch(|out| unsafe { crate::hl_obj_iternext(iter.0, out) }) {
+            Ok(item) if !item.0.is_null() => Some(Ok(item)),
+            result => {
+                self.0 = None;
+                result.err().map(Err)
+            }
+        }
+    }
+}
+
+/// An object kept alive by the garbage collector until the root is dropped,
+/// wherever the [`MpRoot`] is stored.
+/// Only [`gc_collect`] scans the roots: guests must route MicroPython's `gc_collect`
+/// to it, e.g. by linking with `--wrap=gc_collect` like `python-host`.
+/// There are 64 root slots; root a `list` to keep more objects alive.
+#[derive(Debug)]
+pub struct MpRoot {
+    obj: MpObj,
+    slot: usize,
+}
+
+impl MpRoot {
+    /// Root an object
+    /// # Errors
+    /// Returns a `MemoryError` if all the root slots are taken.
+    pub fn new(obj: MpObj) -> MpResult<Self> {
+        let mut slot = 0;
+
+        match unsafe { crate::hl_gc_root_add(obj.0, &mut slot) } {
+            0 => Ok(Self { obj, slot }),
+            _ => Err(MpError::new(MpExcType::MemoryError, "no free GC root slot")),
+        }
+    }
+
+    /// The rooted object
+    pub fn get(&self) -> MpObj {
+        self.obj
+    }
+}
+
+impl Drop for MpRoot {
+    fn drop(&mut self) {
+        unsafe { crate::hl_gc_root_remove(self.slot) }
+    }
+}
+
+/// Run a garbage collection, scanning the registers, the stack and the [`MpRoot`]s.
+/// Guests route MicroPython's `gc_collect` here.
+pub fn gc_collect() {
+    unsafe { crate::hl_gc_collect() }
+}
+
+/// Run `f` under an NLR handler, turning the exception it raises into an error.
+/// Meant for calling the raw MicroPython API, which raises by jumping back to the handler.
+/// # Safety
+/// An exception skips the rest of `f`, and the destructors of its locals:
+/// `f` must not own values that need dropping, e.g. `String`s or [`MpRoot`]s,
+/// when it calls into MicroPython.
+pub unsafe fn nlr_protect<F: FnOnce() -> R, R>(f: F) -> MpResult<R> {
+    unsafe extern "C" fn trampoline<F: FnOnce() -> R, R>(data: *mut c_void) {
+        let (f, result) = unsafe { &mut *(data as *mut (Option<F>, Option<R>)) };
+        *result = f.take().map(|f| f());
+    }
+
+    let mut state = (Some(f), None);
+    let data = &mut state as *mut _ as *mut c_void;
+    catch(|exc| unsafe { crate::hl_nlr_call(Some(trampoline::<F, R>), data, exc) })?;
+
+    state
+        .1
+        .ok_or_else(|| MpError::runtime_error("NLR protected function didn't return"))
+}
+
+impl From<bool> for MpObj {
+    fn from(value: bool) -> Self {
+        MpObj::new_bool(value)
+    }
+}
+
+impl TryFrom<i64> for MpObj {
+    type Error = MpError;
+
+    fn try_from(value: i64) -> MpResult<Self> {
+        MpObj::new_int(value)
+    }
+}
+
+impl TryFrom<f64> for MpObj {
+    type Error = MpError;
+
+    fn try_from(value: f64) -> MpResult<Self> {
+        MpObj::new_float(value)
+    }
+}
+
+impl TryFrom<&str> for MpObj {
+    type Error = MpError;
+
+    fn try_from(value: &str) -> MpResult<Self> {
+        MpObj::new_str(value)
+    }
+}
+
+impl TryFrom<String> for MpObj {
+    type Error = MpError;
+
+    fn try_from(value: String) -> MpResult<Self> {
+        MpObj::new_str(&value)
+    }
+}
+
+impl TryFrom<&[u8]> for MpObj {
+    type Error = MpError;
+
+    fn try_from(value: &[u8]) -> MpResult<Self> {
+        MpObj::new_bytes(value)
+    }
+}
+
+impl TryFrom<MpObj> for bool {
+    type Error = MpError;
+
+    fn try_from(obj: MpObj) -> MpResult<Self> {
+        obj.is_true()
+    }
+}
+
+impl TryFrom<MpObj> for i64 {
+    type Error = MpError;
+
+    fn try_from(obj: MpObj) -> MpResult<Self> {
+        obj.as_int()
+    }
+}
+
+impl TryFrom<MpObj> for f64 {
+    type Error = MpError;
+
+    fn try_from(obj: MpObj) -> MpResult<Self> {
+        obj.as_float()
+    }
+}
+
+impl TryFrom<MpObj> for String {
+    type Error = MpError;
+
+    fn try_from(obj: MpObj) -> MpResult<Self> {
+        // Copied before anything allocates
+        unsafe { obj.as_str() }.map(String::from)
+    }
+}
+
+impl TryFrom<MpObj> for Vec<u8> {
+    type Error = MpError;
+
+    fn try_from(obj: MpObj) -> MpResult<Self> {
+        // Copied before anything allocates
+        unsafe { obj.as_bytes() }.map(<[u8]>::to_vec)
+    }
+}
+
+impl From<Infallible> for MpError {
+    fn from(e: Infallible) -> Self {
+        match e {}
+    }
+}
+
+/// A `list` of the converted items.
+/// Each item is appended to the list as soon as it is converted, so the
+/// items already converted are kept alive by the list.
+impl<T> TryFrom<Vec<T>> for MpObj
+where
+    T: TryInto<MpObj>,
+    MpError: From<T::Error>,
+{
+    type Error = MpError;
+
+    fn try_from(items: Vec<T>) -> MpResult<Self> {
+        let list = MpObj::new_list(&[])?;
+        for item in items {
+            list.append(item.try_into()?)?;
+        }
+
+        Ok(list)
+    }
+}
+
+/// The converted items of a `list` or `tuple`.
+/// A `Vec<MpObj>` holds the items themselves, alive as long as the list or tuple
+/// holds them, see [`MpObj`].
+impl<T> TryFrom<MpObj> for Vec<T>
+where
+    T: TryFrom<MpObj>,
+    MpError: From<T::Error>,
+{
+    type Error = MpError;
+
+    fn try_from(obj: MpObj) -> MpResult<Self> {
+        obj.items()?
+            .into_iter()
+            .map(|item| Ok(T::try_from(item)?))
+            .collect()
+    }
+}
+
+/// A `dict` of the converted keys and values
+impl<K, V> TryFrom<BTreeMap<K, V>> for MpObj
+where
+    K: TryInto<MpObj>,
+    V: TryInto<MpObj>,
+    MpError: From<K::Error> + From<V::Error>,
+{
+    type Error = MpError;
+
+    fn try_from(map: BTreeMap<K, V>) -> MpResult<Self> {
+        let dict = MpObj::new_dict()?;
+        for (key, value) in map {
+            let key = key.try_into()?;
+            dict.set_item(key, value.try_into()?)?;
+        }
+
+        Ok(dict)
+    }
+}
+
+/// The converted keys and values of a `dict`, or of any object with an `items()`
+/// method. Keys converting to the same Rust value keep the last value.
+/// Keys and values of type [`MpObj`] are alive as long as the dict holds them.
+impl<K, V> TryFrom<MpObj> for BTreeMap<K, V>
+where
+    K: TryFrom<MpObj> + Ord,
+    V: TryFrom<MpObj>,
+    MpError: From<K::Error> + From<V::Error>,
+{
+    type Error = MpError;
+
+    fn try_from(obj: MpObj) -> MpResult<Self> {
+        let mut map = BTreeMap::new();
+        for item in obj.call_method("items", &[])?.iter()? {
+            let &[key, value] = item?.items()?.as_slice() else {
+                return Err(MpError::type_error(
+                    "items() must return key and value pairs",
+                ));
+            };
+            map.insert(K::try_from(key)?, V::try_from(value)?);
+        }
+
+        Ok(map)
+    }
+}
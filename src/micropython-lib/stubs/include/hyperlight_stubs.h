@@ -0,0 +1,190 @@
+/*
+ * Functions implemented in micropython_stubs.c and used from Rust.
+ */
+
+#ifndef HYPERLIGHT_STUBS_H
+#define HYPERLIGHT_STUBS_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#include "py/obj.h"
+
+/*
+ * Compile and execute a Python source string in the __main__ module.
+ * Uncaught exceptions are printed.
+ * Returns 0 on success or 1 if the code raised an exception.
+ */
+int hl_exec_str(const char *src);
+
+/*
+ * Text of the exception raised by the last hl_exec_str, hl_eval_str or
+ * hl_call_function call, truncated to 1024 bytes.
+ * Sets len to 0 if the last call didn't raise.
+ */
+const char *hl_last_exception(size_t *len);
+
+/*
+ * Evaluate a Python expression in the __main__ module.
+ * The encoded value, or the text of the exception raised, is handed over
+ * through hl_result_set.
+ * Returns 0 on success or 1 if the expression raised an exception.
+ */
+int hl_eval_str(const char *src);
+
+/*
+ * Call a function of the __main__ module with the encoded tuple of arguments.
+ * The encoded return value, or the text of the exception raised, is handed over
+ * through hl_result_set.
+ * Returns 0 on success or 1 if the call raised an exception.
+ */
+int hl_call_function(const char *name, const uint8_t *args, size_t args_len);
+
+/*
+ * Decode a JSON document with json.loads and store it as the global name
+ * of the __main__ module.
+ * The encoded None, or the text of the exception raised, is handed over
+ * through hl_result_set.
+ * Returns 0 on success or 1 if the document couldn't be decoded.
+ */
+int hl_set_global_json(const char *name, const char *json, size_t json_len);
+
+/*
+ * Evaluate a Python expression in the __main__ module and encode its value
+ * with json.dumps.
+ * The encoded JSON string, or the text of the exception raised, is handed over
+ * through hl_result_set.
+ * Returns 0 on success or 1 if the expression or the encoding raised an exception.
+ */
+int hl_eval_json(const char *src);
+
+/* Direct calls to __import__ are allowed */
+#define HL_IMPORT_CALL_ALLOWED (0)
+/* Direct calls to __import__ raise a PermissionError naming the policy */
+#define HL_IMPORT_CALL_DENIED (1)
+/* Direct calls to __import__ raise a NameError */
+#define HL_IMPORT_CALL_REMOVED (2)
+
+/*
+ * Set how the builtin policy handles direct calls to __import__, one of the
+ * HL_IMPORT_CALL_* modes. Import statements are not affected, only the module
+ * policy applies to them. Telling them apart requires linking with
+ * --wrap=mp_import_name.
+ * With HL_IMPORT_CALL_DENIED, PermissionError must be defined in the builtins.
+ */
+void hl_set_import_call_policy(int mode, const char *policy, size_t len);
+
+/*
+ * Seed the random module, whether or not it was imported yet. The 32-bit
+ * generator is seeded with the high 32 bits of seed folded into the low ones.
+ * Returns 0 on success or 1 if the module raised an exception.
+ */
+int hl_random_seed(uint64_t seed);
+
+/*
+ * Default maximum number of backtracking steps of a single regular expression match.
+ */
+#define HL_RE_BACKTRACK_LIMIT_DEFAULT           (1000000)
+
+/*
+ * Set the maximum number of backtracking steps of a single regular expression
+ * match, past which it raises a RuntimeError. 0 removes the limit.
+ */
+void hl_re_set_backtrack_limit(size_t limit);
+
+/*
+ * Objects and native modules used from Rust.
+ * Unless noted otherwise, these return 0 on success, or 1 with the exception
+ * MicroPython raised stored in *out or *exc. They never raise.
+ */
+
+/*
+ * A Python function implemented in Rust, called with its data and the positional
+ * arguments. Returns 0 with the return value in *result, or 1 with the exception
+ * to raise in *result.
+ */
+typedef int (*hl_native_fun_t)(const void *data, size_t n_args, const mp_obj_t *args, mp_obj_t *result);
+
+/*
+ * Create an exception of the type at the given index of hl_exception_types,
+ * with a message. Returns the exception raised creating it if that fails.
+ */
+mp_obj_t hl_exception_new(size_t type, const char *msg, size_t len);
+
+mp_obj_t hl_obj_none(void);
+mp_obj_t hl_obj_bool(bool value);
+const char *hl_obj_type_name(mp_obj_t obj);
+
+int hl_obj_new_int(int64_t value, mp_obj_t *out);
+int hl_obj_new_float(double value, mp_obj_t *out);
+int hl_obj_new_str(const char *data, size_t len, mp_obj_t *out);
+int hl_obj_new_bytes(const uint8_t *data, size_t len, mp_obj_t *out);
+int hl_obj_new_tuple(const mp_obj_t *items, size_t len, mp_obj_t *out);
+int hl_obj_new_list(const mp_obj_t *items, size_t len, mp_obj_t *out);
+
+/*
+ * Conversions of a Python object, raising a TypeError for the wrong types.
+ * Strings and buffers are borrowed from the object.
+ */
+int hl_obj_is_true(mp_obj_t obj, bool *value, mp_obj_t *exc);
+int hl_obj_get_int(mp_obj_t obj, int64_t *value, mp_obj_t *exc);
+int hl_obj_get_float(mp_obj_t obj, double *value, mp_obj_t *exc);
+int hl_obj_get_str(mp_obj_t obj, const char **data, size_t *len, mp_obj_t *exc);
+int hl_obj_get_buffer(mp_obj_t obj, const uint8_t **data, size_t *len, mp_obj_t *exc);
+
+/*
+ * Create a Python function named name calling fun with data.
+ */
+int hl_native_fun_new(const char *name, size_t len, hl_native_fun_t fun, const void *data, mp_obj_t *out);
+
+/*
+ * Create a module in the table of native modules, importable by its name like
+ * a built-in module.
+ */
+int hl_module_new(const char *name, size_t len, mp_obj_t *out);
+
+/*
+ * Containers, attributes and calls.
+ * Items of a list or tuple are borrowed from it. hl_obj_iternext stores
+ * MP_OBJ_NULL in *out once the iterator is exhausted.
+ */
+int hl_obj_new_dict(mp_obj_t *out);
+int hl_obj_str(mp_obj_t obj, bool repr, mp_obj_t *out);
+int hl_obj_len(mp_obj_t obj, size_t *len, mp_obj_t *exc);
+int hl_obj_get_array(mp_obj_t obj, mp_obj_t **items, size_t *len, mp_obj_t *exc);
+int hl_obj_list_append(mp_obj_t list, mp_obj_t item, mp_obj_t *exc);
+int hl_obj_get_item(mp_obj_t obj, mp_obj_t key, mp_obj_t *out);
+int hl_obj_set_item(mp_obj_t obj, mp_obj_t key, mp_obj_t value, mp_obj_t *exc);
+int hl_obj_load_attr(mp_obj_t obj, const char *name, size_t len, mp_obj_t *out);
+int hl_obj_store_attr(mp_obj_t obj, const char *name, size_t len, mp_obj_t value, mp_obj_t *exc);
+int hl_obj_call(mp_obj_t fun, const mp_obj_t *args, size_t n_args, mp_obj_t *out);
+int hl_obj_getiter(mp_obj_t obj, mp_obj_t *out);
+int hl_obj_iternext(mp_obj_t iter, mp_obj_t *out);
+int hl_obj_import(const char *name, size_t len, mp_obj_t *out);
+
+/*
+ * Call fun(data) under an NLR handler.
+ */
+int hl_nlr_call(void (*fun)(void *), void *data, mp_obj_t *exc);
+
+/*
+ * Number of objects Rust code can keep alive at once.
+ */
+#define HL_GC_ROOTS_MAX                         (64)
+
+/*
+ * Keep an object alive across garbage collections, in a free root slot.
+ * Returns 0 with the slot in *slot, or 1 if all the slots are taken.
+ */
+int hl_gc_root_add(mp_obj_t obj, size_t *slot);
+void hl_gc_root_remove(size_t slot);
+
+/*
+ * Garbage collection scanning the registers, the stack and the root slots.
+ * gc_collect must be routed here for the root slots to be scanned, e.g. by
+ * linking with --wrap=gc_collect (see python-host).
+ */
+void hl_gc_collect(void);
+
+#endif // HYPERLIGHT_STUBS_H
@@ -8,6 +8,7 @@
 #ifndef MPCONFIGPORT_H
 #define MPCONFIGPORT_H
 
+#include <stddef.h>
 #include <stdint.h>
 
 // ============================================================================
@@ -19,8 +20,86 @@
 typedef long mp_off_t;
 typedef long ssize_t;
 
-// Use minimum ROM level - no fancy features
+// ============================================================================
+// Feature profile, selected by the profile-* cargo features (see build.rs)
+// ============================================================================
+
+#define HL_PROFILE_MINIMAL                      (0)
+#define HL_PROFILE_CORE                         (1)
+#define HL_PROFILE_EXTRA                        (2)
+#define HL_PROFILE_FULL                         (3)
+
+#ifndef HL_PROFILE
+#define HL_PROFILE                              (HL_PROFILE_MINIMAL)
+#endif
+
+#if HL_PROFILE >= HL_PROFILE_FULL
+#define MICROPY_CONFIG_ROM_LEVEL                (MICROPY_CONFIG_ROM_LEVEL_FULL_FEATURES)
+#elif HL_PROFILE >= HL_PROFILE_EXTRA
+#define MICROPY_CONFIG_ROM_LEVEL                (MICROPY_CONFIG_ROM_LEVEL_EXTRA_FEATURES)
+#elif HL_PROFILE >= HL_PROFILE_CORE
+#define MICROPY_CONFIG_ROM_LEVEL                (MICROPY_CONFIG_ROM_LEVEL_CORE_FEATURES)
+#else
 #define MICROPY_CONFIG_ROM_LEVEL                (MICROPY_CONFIG_ROM_LEVEL_MINIMUM)
+#endif
+
+// Modules from extmod compiled for the profile, must match EXTMOD_MODULES in build.rs.
+// json is always built, the host exchanges JSON documents with the sandbox.
+// re is always built too, with a backtracking limit (see stubs/re1.5).
+#define MICROPY_PY_JSON                         (1)
+#define MICROPY_PY_RE                           (1)
+#define MICROPY_PY_RE_MATCH_GROUPS              (1)
+#define MICROPY_PY_RE_MATCH_SPAN_START_END      (1)
+#define MICROPY_PY_RE_SUB                       (1)
+// time is always built, reading the host clock (see python-host/src/clock.rs).
+#define MICROPY_PY_TIME                         (1)
+#define MICROPY_PY_TIME_TIME_TIME_NS            (1)
+// random is always built, seeded from the host on first import or for a run
+// (see hl_random_seed in micropython_stubs.c).
+#define MICROPY_PY_RANDOM                       (1)
+#define MICROPY_PY_RANDOM_EXTRA_FUNCS           (1)
+#define MICROPY_PY_RANDOM_SEED_INIT_FUNC        (hl_random_entropy())
+#define MICROPY_MODULE_BUILTIN_INIT             (1)
+#define MICROPY_PY_HEAPQ                        (HL_PROFILE >= HL_PROFILE_CORE)
+#define MICROPY_PY_BINASCII                     (HL_PROFILE >= HL_PROFILE_EXTRA)
+#define MICROPY_PY_ERRNO                        (HL_PROFILE >= HL_PROFILE_EXTRA)
+#define MICROPY_PY_PLATFORM                     (HL_PROFILE >= HL_PROFILE_EXTRA)
+
+// The other extmod modules need OS support or libraries the guest doesn't have
+#define MICROPY_PY_ASYNCIO                      (0)
+#define MICROPY_PY_BINASCII_CRC32               (0)
+#define MICROPY_PY_CRYPTOLIB                    (0)
+#define MICROPY_PY_DEFLATE                      (0)
+#define MICROPY_PY_FRAMEBUF                     (0)
+#define MICROPY_PY_HASHLIB                      (0)
+#define MICROPY_PY_MACHINE                      (0)
+#define MICROPY_PY_OS                           (0)
+#define MICROPY_PY_SELECT                       (0)
+#define MICROPY_PY_UCTYPES                      (0)
+
+// No stdin to read from
+#define MICROPY_PY_BUILTINS_INPUT               (0)
+
+// ============================================================================
+// Frozen modules: Python modules compiled to bytecode at build time, listed by
+// the HYPERLIGHT_PYTHON_FROZEN manifest (see build.rs)
+// ============================================================================
+
+#ifndef HL_FROZEN
+#define HL_FROZEN                               (0)
+#endif
+
+#if HL_FROZEN
+#define MICROPY_MODULE_FROZEN_MPY               (1)
+#define MICROPY_QSTR_EXTRA_POOL                 (mp_qstr_frozen_const_pool)
+// Frozen modules are imported through the ".frozen" entry of sys.path
+#define MICROPY_ENABLE_EXTERNAL_IMPORT          (1)
+#define MICROPY_PY_SYS                          (1)
+#define MICROPY_PY_SYS_PATH                     (1)
+#define MICROPY_PY_SYS_STDFILES                 (0)
+// Digit size of the ints frozen by mpy-tool
+#define MPZ_DIG_SIZE                            (16)
+#endif
 
 // Use MicroPython's internal GC (required for bare metal)
 #define MICROPY_ENABLE_COMPILER                 (1)
@@ -28,11 +107,50 @@ typedef long ssize_t;
 
 // Disable all optional modules that require OS support
 #define MICROPY_PY_GC                           (1)
+#ifndef MICROPY_PY_SYS
 #define MICROPY_PY_SYS                          (0)
+#endif
 #define MICROPY_PY_ARRAY                        (1)
 
+// Floating point: double precision floats and complex numbers with the math and
+// cmath modules. The libm routines are compiled from lib/libm_dbl (see build.rs).
+#define MICROPY_FLOAT_IMPL                      (MICROPY_FLOAT_IMPL_DOUBLE)
+#define MICROPY_PY_BUILTINS_COMPLEX             (1)
+#define MICROPY_PY_MATH                         (1)
+#define MICROPY_PY_MATH_SPECIAL_FUNCTIONS       (1)
+#define MICROPY_PY_CMATH                        (1)
+
+// Arbitrary precision integers
+#define MICROPY_LONGINT_IMPL                    (MICROPY_LONGINT_IMPL_MPZ)
+
+// Raise RuntimeError on runaway recursion instead of overflowing the guest stack.
+// The limit is set from the sandbox stack size when the runtime is initialized.
+#define MICROPY_STACK_CHECK                     (1)
+
+// Track allocation totals for the per-run execution stats
+#define MICROPY_MEM_STATS                       (1)
+
 #define MICROPY_MPHALPORT_H                     "port/mphalport.h"
 
+// ============================================================================
+// Policies: imports and builtins are subject to the sandbox policies
+// ============================================================================
+
+#define mp_builtin___import__                   hl_builtin___import__
+
+// Allow the sandbox builtin policy to replace entries of the builtins module
+#define MICROPY_CAN_OVERRIDE_BUILTINS           (1)
+
+// ============================================================================
+// Output: all Python output goes through the guest so it can be accounted for
+// ============================================================================
+
+extern void hl_stdout_write(const char *str, size_t len);
+
+// Entropy from the host RNG seeding the random module (see micropython_stubs.c)
+extern uint64_t hl_random_entropy(void);
+#define MP_PLAT_PRINT_STRN(str, len)            hl_stdout_write(str, len)
+
 // Provide a simple port configuration
 #define MICROPY_HW_BOARD_NAME                   "Hyperlight-x86"
 #define MICROPY_HW_MCU_NAME                     "x86_64"
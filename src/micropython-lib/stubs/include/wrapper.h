@@ -4,3 +4,7 @@
  */
 
 #include "port/micropython_embed.h"
+#include "hyperlight_stubs.h"
+#include "py/gc.h"
+#include "py/misc.h"
+#include "py/stackctrl.h"
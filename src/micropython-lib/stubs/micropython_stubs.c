@@ -8,6 +8,21 @@
 #include <stdarg.h>
 #include <stddef.h>
 #include <stdint.h>
+#include <string.h>
+
+#include "py/builtin.h"
+#include "py/compile.h"
+#include "py/gc.h"
+#include "py/mperrno.h"
+#include "py/mphal.h"
+#include "py/objlist.h"
+#include "py/objtuple.h"
+#include "py/parsenum.h"
+#include "py/runtime.h"
+#include "py/stackctrl.h"
+#include "shared/runtime/gchelper.h"
+
+#include "hyperlight_stubs.h"
 
 /* ============================================================================
  * External functions provided by Rust/Hyperlight
@@ -18,3 +33,954 @@
 extern void hl_print_char(char c);
 extern void hl_print_str(const char *s);
 extern void hl_abort(void) __attribute__((noreturn));
+extern bool hl_module_allowed(const char *name, size_t len);
+extern int hl_output_write(const char *str, size_t len);
+extern void hl_result_set(const uint8_t *data, size_t len);
+extern bool hl_time_ns(uint64_t *out);
+extern bool hl_ticks_ns(uint64_t *out);
+extern bool hl_sleep_ns(uint64_t ns);
+extern bool hl_host_random_seed(uint64_t *out);
+
+// Status returned by hl_output_write, must match python-host/src/output.rs
+#define HL_OUTPUT_OK                            (0)
+#define HL_OUTPUT_ABORT                         (1)
+#define HL_OUTPUT_ERROR                         (2)
+
+/* ============================================================================
+ * Execution and output
+ * ============================================================================
+ */
+
+// Whether an exception can be raised from the print path, i.e. the code
+// executed by hl_exec_str is running under its NLR handler.
+static bool hl_exec_active = false;
+
+// Longest exception text kept for the host, the rest is dropped
+#define HL_EXCEPTION_TEXT_MAX                   (1024)
+
+// Fixed size buffer collecting the text of an exception without allocating
+typedef struct _hl_text_buf_t {
+    char data[HL_EXCEPTION_TEXT_MAX];
+    size_t len;
+} hl_text_buf_t;
+
+static void hl_text_buf_strn(void *env, const char *str, size_t len) {
+    hl_text_buf_t *buf = env;
+    size_t n = MIN(len, sizeof(buf->data) - buf->len);
+
+    memcpy(buf->data + buf->len, str, n);
+    buf->len += n;
+}
+
+// Text of the exception raised by the last hl_exec_str call, if any
+static hl_text_buf_t hl_exception_text;
+
+static void hl_exception_text_set(mp_obj_t exc) {
+    mp_print_t print = {&hl_exception_text, hl_text_buf_strn};
+
+    hl_exception_text.len = 0;
+    mp_obj_print_exception(&print, exc);
+}
+
+const char *hl_last_exception(size_t *len) {
+    *len = hl_exception_text.len;
+    return hl_exception_text.data;
+}
+
+int hl_exec_str(const char *src) {
+    hl_exception_text.len = 0;
+
+    nlr_buf_t nlr;
+    if (nlr_push(&nlr) == 0) {
+        hl_exec_active = true;
+
+        mp_lexer_t *lex = mp_lexer_new_from_str_len(MP_QSTR__lt_stdin_gt_, src, strlen(src), 0);
+        qstr source_name = lex->source_name;
+        mp_parse_tree_t parse_tree = mp_parse(lex, MP_PARSE_FILE_INPUT);
+        mp_obj_t module_fun = mp_compile(&parse_tree, source_name, true);
+        mp_call_function_0(module_fun);
+
+        nlr_pop();
+        hl_exec_active = false;
+        return 0;
+    } else {
+        // Printing the exception must not raise again
+        hl_exec_active = false;
+        mp_obj_print_exception(&mp_plat_print, MP_OBJ_FROM_PTR(nlr.ret_val));
+        hl_exception_text_set(MP_OBJ_FROM_PTR(nlr.ret_val));
+        return 1;
+    }
+}
+
+// Target of MP_PLAT_PRINT_STRN (see mpconfigport.h).
+// The output limit is enforced in Rust, this only raises the exception the
+// limit policy asks for. It is raised once per run so that the script can
+// still be unwound and its traceback printed (and dropped).
+void hl_stdout_write(const char *str, size_t len) {
+    int status = hl_output_write(str, len);
+
+    if (status == HL_OUTPUT_OK || !hl_exec_active) {
+        return;
+    }
+
+    hl_exec_active = false;
+    if (status == HL_OUTPUT_ABORT) {
+        mp_raise_type(&mp_type_SystemExit);
+    } else {
+        mp_raise_msg(&mp_type_RuntimeError, MP_ERROR_TEXT("output limit exceeded"));
+    }
+}
+
+/* ============================================================================
+ * Values exchanged with the host
+ * ============================================================================
+ */
+
+// Tags of the encoded values, must match hyperlight-python/src/sandbox/py_value.rs.
+// Integers, floats and lengths are little-endian; lengths and counts are u32.
+// Ints that don't fit a small int are sent as decimal strings (HL_VALUE_BIG_INT).
+#define HL_VALUE_NONE                           (0)
+#define HL_VALUE_FALSE                          (1)
+#define HL_VALUE_TRUE                           (2)
+#define HL_VALUE_INT                            (3)
+#define HL_VALUE_FLOAT                          (4)
+#define HL_VALUE_STR                            (5)
+#define HL_VALUE_BYTES                          (6)
+#define HL_VALUE_LIST                           (7)
+#define HL_VALUE_TUPLE                          (8)
+#define HL_VALUE_DICT                           (9)
+#define HL_VALUE_BIG_INT                        (10)
+
+static void hl_value_put(vstr_t *out, const void *data, size_t len) {
+    vstr_add_strn(out, (const char *)data, len);
+}
+
+static void hl_value_put_len(vstr_t *out, uint8_t tag, size_t len) {
+    uint32_t len32 = (uint32_t)len;
+
+    vstr_add_byte(out, tag);
+    hl_value_put(out, &len32, sizeof(len32));
+}
+
+// Encode a Python object, raising a TypeError for types the host doesn't know
+static void hl_value_encode(vstr_t *out, mp_obj_t obj) {
+    MP_STACK_CHECK();
+
+    if (obj == mp_const_none) {
+        vstr_add_byte(out, HL_VALUE_NONE);
+    } else if (obj == mp_const_false) {
+        vstr_add_byte(out, HL_VALUE_FALSE);
+    } else if (obj == mp_const_true) {
+        vstr_add_byte(out, HL_VALUE_TRUE);
+    } else if (mp_obj_is_small_int(obj)) {
+        int64_t value = MP_OBJ_SMALL_INT_VALUE(obj);
+        vstr_add_byte(out, HL_VALUE_INT);
+        hl_value_put(out, &value, sizeof(value));
+    } else if (mp_obj_is_int(obj)) {
+        vstr_t digits;
+        mp_print_t print;
+        vstr_init_print(&digits, 32, &print);
+        mp_obj_print_helper(&print, obj, PRINT_STR);
+        hl_value_put_len(out, HL_VALUE_BIG_INT, digits.len);
+        hl_value_put(out, digits.buf, digits.len);
+        vstr_clear(&digits);
+#if MICROPY_PY_BUILTINS_FLOAT
+    } else if (mp_obj_is_float(obj)) {
+        double value = mp_obj_get_float(obj);
+        vstr_add_byte(out, HL_VALUE_FLOAT);
+        hl_value_put(out, &value, sizeof(value));
+#endif
+    } else if (mp_obj_is_str(obj) || mp_obj_is_type(obj, &mp_type_bytes)) {
+        size_t len;
+        const char *data = mp_obj_str_get_data(obj, &len);
+        hl_value_put_len(out, mp_obj_is_str(obj) ? HL_VALUE_STR : HL_VALUE_BYTES, len);
+        hl_value_put(out, data, len);
+    } else if (mp_obj_is_type(obj, &mp_type_list) || mp_obj_is_type(obj, &mp_type_tuple)) {
+        size_t len;
+        mp_obj_t *items;
+        uint8_t tag;
+        if (mp_obj_is_type(obj, &mp_type_list)) {
+            mp_obj_list_get(obj, &len, &items);
+            tag = HL_VALUE_LIST;
+        } else {
+            mp_obj_tuple_get(obj, &len, &items);
+            tag = HL_VALUE_TUPLE;
+        }
+        hl_value_put_len(out, tag, len);
+        for (size_t i = 0; i < len; i++) {
+            hl_value_encode(out, items[i]);
+        }
+    } else if (mp_obj_is_type(obj, &mp_type_dict)) {
+        mp_map_t *map = mp_obj_dict_get_map(obj);
+        hl_value_put_len(out, HL_VALUE_DICT, map->used);
+        for (size_t i = 0; i < map->alloc; i++) {
+            if (mp_map_slot_is_filled(map, i)) {
+                hl_value_encode(out, map->table[i].key);
+                hl_value_encode(out, map->table[i].value);
+            }
+        }
+    } else {
+        mp_raise_msg_varg(&mp_type_TypeError,
+            MP_ERROR_TEXT("can't convert '%s' to a host value"), mp_obj_get_type_str(obj));
+    }
+}
+
+// Encoded value being decoded
+typedef struct _hl_value_reader_t {
+    const uint8_t *data;
+    size_t len;
+} hl_value_reader_t;
+
+static const uint8_t *hl_value_take(hl_value_reader_t *reader, size_t len) {
+    if (len > reader->len) {
+        mp_raise_ValueError(MP_ERROR_TEXT("invalid host value"));
+    }
+
+    const uint8_t *data = reader->data;
+    reader->data += len;
+    reader->len -= len;
+    return data;
+}
+
+// Read a length or count, which can't exceed the remaining bytes
+static size_t hl_value_take_len(hl_value_reader_t *reader) {
+    uint32_t len;
+    memcpy(&len, hl_value_take(reader, sizeof(len)), sizeof(len));
+
+    if (len > reader->len) {
+        mp_raise_ValueError(MP_ERROR_TEXT("invalid host value"));
+    }
+    return len;
+}
+
+// Decode a value sent by the host into a Python object
+static mp_obj_t hl_value_decode(hl_value_reader_t *reader) {
+    MP_STACK_CHECK();
+
+    switch (*hl_value_take(reader, 1)) {
+        case HL_VALUE_NONE:
+            return mp_const_none;
+        case HL_VALUE_FALSE:
+            return mp_const_false;
+        case HL_VALUE_TRUE:
+            return mp_const_true;
+        case HL_VALUE_INT: {
+            int64_t value;
+            memcpy(&value, hl_value_take(reader, sizeof(value)), sizeof(value));
+            return mp_obj_new_int((mp_int_t)value);
+        }
+        case HL_VALUE_BIG_INT: {
+            size_t len = hl_value_take_len(reader);
+            return mp_parse_num_integer((const char *)hl_value_take(reader, len), len, 10, NULL);
+        }
+        case HL_VALUE_FLOAT: {
+#if MICROPY_PY_BUILTINS_FLOAT
+            double value;
+            memcpy(&value, hl_value_take(reader, sizeof(value)), sizeof(value));
+            return mp_obj_new_float((mp_float_t)value);
+#else
+            mp_raise_msg(&mp_type_TypeError, MP_ERROR_TEXT("floats aren't supported"));
+#endif
+        }
+        case HL_VALUE_STR: {
+            size_t len = hl_value_take_len(reader);
+            return mp_obj_new_str((const char *)hl_value_take(reader, len), len);
+        }
+        case HL_VALUE_BYTES: {
+            size_t len = hl_value_take_len(reader);
+            return mp_obj_new_bytes(hl_value_take(reader, len), len);
+        }
+        case HL_VALUE_LIST: {
+            size_t len = hl_value_take_len(reader);
+            mp_obj_t list = mp_obj_new_list(0, NULL);
+            for (size_t i = 0; i < len; i++) {
+                mp_obj_list_append(list, hl_value_decode(reader));
+            }
+            return list;
+        }
+        case HL_VALUE_TUPLE: {
+            size_t len = hl_value_take_len(reader);
+            mp_obj_tuple_t *tuple = MP_OBJ_TO_PTR(mp_obj_new_tuple(len, NULL));
+            for (size_t i = 0; i < len; i++) {
+                tuple->items[i] = hl_value_decode(reader);
+            }
+            return MP_OBJ_FROM_PTR(tuple);
+        }
+        case HL_VALUE_DICT: {
+            size_t len = hl_value_take_len(reader);
+            mp_obj_t dict = mp_obj_new_dict(0);
+            for (size_t i = 0; i < len; i++) {
+                mp_obj_t key = hl_value_decode(reader);
+                mp_obj_dict_store(dict, key, hl_value_decode(reader));
+            }
+            return dict;
+        }
+        default:
+            mp_raise_ValueError(MP_ERROR_TEXT("invalid host value"));
+    }
+}
+
+// Run fun under an NLR handler and hand over its encoded result, or the text
+// of the exception it raised, to the host through hl_result_set.
+static int hl_value_call(mp_obj_t (*fun)(const void *arg), const void *arg) {
+    hl_exception_text.len = 0;
+
+    nlr_buf_t nlr;
+    if (nlr_push(&nlr) == 0) {
+        hl_exec_active = true;
+        mp_obj_t value = fun(arg);
+        hl_exec_active = false;
+
+        vstr_t out;
+        vstr_init(&out, 32);
+        hl_value_encode(&out, value);
+        hl_result_set((const uint8_t *)out.buf, out.len);
+        vstr_clear(&out);
+
+        nlr_pop();
+        return 0;
+    } else {
+        hl_exec_active = false;
+        hl_exception_text_set(MP_OBJ_FROM_PTR(nlr.ret_val));
+        hl_result_set((const uint8_t *)hl_exception_text.data, hl_exception_text.len);
+        return 1;
+    }
+}
+
+static mp_obj_t hl_eval_fun(const void *arg) {
+    const char *src = arg;
+
+    mp_lexer_t *lex = mp_lexer_new_from_str_len(MP_QSTR__lt_stdin_gt_, src, strlen(src), 0);
+    qstr source_name = lex->source_name;
+    mp_parse_tree_t parse_tree = mp_parse(lex, MP_PARSE_EVAL_INPUT);
+    mp_obj_t module_fun = mp_compile(&parse_tree, source_name, false);
+    return mp_call_function_0(module_fun);
+}
+
+int hl_eval_str(const char *src) {
+    return hl_value_call(hl_eval_fun, src);
+}
+
+// Arguments of hl_call_function
+typedef struct _hl_call_t {
+    const char *name;
+    hl_value_reader_t args;
+} hl_call_t;
+
+static mp_obj_t hl_call_fun(const void *arg) {
+    hl_call_t call = *(const hl_call_t *)arg;
+
+    mp_obj_t args = hl_value_decode(&call.args);
+    if (!mp_obj_is_type(args, &mp_type_tuple) || call.args.len != 0) {
+        mp_raise_ValueError(MP_ERROR_TEXT("invalid host value"));
+    }
+
+    size_t n_args;
+    mp_obj_t *items;
+    mp_obj_tuple_get(args, &n_args, &items);
+
+    mp_obj_t fun = mp_load_global(qstr_from_str(call.name));
+    return mp_call_function_n_kw(fun, n_args, 0, items);
+}
+
+int hl_call_function(const char *name, const uint8_t *args, size_t args_len) {
+    hl_call_t call = {name, {args, args_len}};
+
+    return hl_value_call(hl_call_fun, &call);
+}
+
+/* ============================================================================
+ * JSON exchanged with the host
+ * ============================================================================
+ */
+
+// The json module is always built (see mpconfigport.h) and used directly,
+// so the sandbox module policy doesn't apply to these host calls.
+extern const mp_obj_module_t mp_module_json;
+
+static mp_obj_t hl_json_fun(qstr name) {
+    return mp_load_attr(MP_OBJ_FROM_PTR(&mp_module_json), name);
+}
+
+// Arguments of hl_set_global_json
+typedef struct _hl_set_global_t {
+    const char *name;
+    const char *json;
+    size_t json_len;
+} hl_set_global_t;
+
+static mp_obj_t hl_set_global_fun(const void *arg) {
+    const hl_set_global_t *set = arg;
+
+    mp_obj_t json = mp_obj_new_str(set->json, set->json_len);
+    mp_obj_t value = mp_call_function_1(hl_json_fun(MP_QSTR_loads), json);
+    mp_store_global(qstr_from_str(set->name), value);
+    return mp_const_none;
+}
+
+int hl_set_global_json(const char *name, const char *json, size_t json_len) {
+    hl_set_global_t set = {name, json, json_len};
+
+    return hl_value_call(hl_set_global_fun, &set);
+}
+
+static mp_obj_t hl_eval_json_fun(const void *arg) {
+    return mp_call_function_1(hl_json_fun(MP_QSTR_dumps), hl_eval_fun(arg));
+}
+
+int hl_eval_json(const char *src) {
+    return hl_value_call(hl_eval_json_fun, src);
+}
+
+/* ============================================================================
+ * Time
+ * ============================================================================
+ */
+
+// The mp_hal_* clock functions read by extmod/modtime.c, on top of the host
+// function calls made in python-host/src/clock.rs. A failed call, e.g. one
+// diverging from a replayed run, raises an OSError into the script.
+#define HL_NANOS_PER_MICRO                      (1000)
+#define HL_NANOS_PER_MILLI                      (1000000)
+
+static uint64_t hl_ticks_ns_or_raise(void) {
+    uint64_t ns;
+
+    if (!hl_ticks_ns(&ns)) {
+        mp_raise_msg(&mp_type_OSError, MP_ERROR_TEXT("HostTicksNs host call failed"));
+    }
+
+    return ns;
+}
+
+static void hl_sleep_ns_or_raise(uint64_t ns) {
+    if (!hl_sleep_ns(ns)) {
+        mp_raise_msg(&mp_type_OSError, MP_ERROR_TEXT("HostSleepNs host call failed"));
+    }
+}
+
+uint64_t mp_hal_time_ns(void) {
+    uint64_t ns;
+
+    if (!hl_time_ns(&ns)) {
+        mp_raise_msg(&mp_type_OSError, MP_ERROR_TEXT("HostTimeNs host call failed"));
+    }
+
+    return ns;
+}
+
+mp_uint_t mp_hal_ticks_ms(void) {
+    return (mp_uint_t)(hl_ticks_ns_or_raise() / HL_NANOS_PER_MILLI);
+}
+
+mp_uint_t mp_hal_ticks_us(void) {
+    return (mp_uint_t)(hl_ticks_ns_or_raise() / HL_NANOS_PER_MICRO);
+}
+
+mp_uint_t mp_hal_ticks_cpu(void) {
+    return (mp_uint_t)hl_ticks_ns_or_raise();
+}
+
+void mp_hal_delay_ms(mp_uint_t ms) {
+    uint64_t ns = (uint64_t)ms * HL_NANOS_PER_MILLI;
+
+    // Saturate like the host clock does rather than wrap around
+    hl_sleep_ns_or_raise(ns / HL_NANOS_PER_MILLI == ms ? ns : UINT64_MAX);
+}
+
+void mp_hal_delay_us(mp_uint_t us) {
+    uint64_t ns = (uint64_t)us * HL_NANOS_PER_MICRO;
+
+    hl_sleep_ns_or_raise(ns / HL_NANOS_PER_MICRO == us ? ns : UINT64_MAX);
+}
+
+// Target of time.time()
+mp_obj_t mp_time_time_get(void) {
+    return mp_obj_new_float((mp_float_t)mp_hal_time_ns() / 1000000000);
+}
+
+/* ============================================================================
+ * Random numbers
+ * ============================================================================
+ */
+
+extern const mp_obj_module_t mp_module_random;
+
+// Target of MICROPY_PY_RANDOM_SEED_INIT_FUNC (see mpconfigport.h), reading the host
+// RNG in python-host/src/random.rs. A failed call raises an OSError into the script.
+uint64_t hl_random_entropy(void) {
+    uint64_t seed;
+
+    if (!hl_host_random_seed(&seed)) {
+        mp_raise_msg(&mp_type_OSError, MP_ERROR_TEXT("HostRandomSeed host call failed"));
+    }
+
+    return seed;
+}
+
+int hl_random_seed(uint64_t seed) {
+    nlr_buf_t nlr;
+    if (nlr_push(&nlr) == 0) {
+        mp_obj_t module = MP_OBJ_FROM_PTR(&mp_module_random);
+
+        // The module seeds itself from the host entropy on its first import,
+        // which would override the seed if it happened later in the run
+        mp_obj_t init[2];
+        mp_load_method_maybe(module, MP_QSTR___init__, init);
+        if (init[0] != MP_OBJ_NULL) {
+            mp_call_method_n_kw(0, 0, init);
+        }
+
+        // The generator keeps a 32-bit state: fold the high bits in instead of dropping them
+        uint32_t folded = (uint32_t)(seed ^ (seed >> 32));
+        mp_call_function_1(mp_load_attr(module, MP_QSTR_seed), mp_obj_new_int_from_uint(folded));
+
+        nlr_pop();
+        return 0;
+    } else {
+        return 1;
+    }
+}
+
+/* ============================================================================
+ * Regular expressions
+ * ============================================================================
+ */
+
+// Read by the re1.5 matcher wrapper (see stubs/re1.5/lib/re1.5/recursiveloop.c)
+size_t hl_re_backtrack_limit = HL_RE_BACKTRACK_LIMIT_DEFAULT;
+
+void hl_re_set_backtrack_limit(size_t limit) {
+    hl_re_backtrack_limit = limit;
+}
+
+/* ============================================================================
+ * Objects and native modules used from Rust (see micropython-lib/src)
+ * ============================================================================
+ */
+
+// The helpers below catch the exceptions MicroPython raises, e.g. a MemoryError,
+// so that they never unwind through Rust frames: they return 0 on success, or 1
+// with the exception raised stored in *exc.
+#define HL_TRY \
+    nlr_buf_t nlr; \
+    if (nlr_push(&nlr) == 0) {
+
+#define HL_CATCH(exc) \
+        nlr_pop(); \
+        return 0; \
+    } \
+    *(exc) = MP_OBJ_FROM_PTR(nlr.ret_val); \
+    return 1;
+
+// Exception types raised from Rust, must match MpExcType in micropython-lib/src/obj.rs
+static const mp_obj_type_t *const hl_exception_types[] = {
+    &mp_type_Exception,
+    &mp_type_TypeError,
+    &mp_type_ValueError,
+    &mp_type_RuntimeError,
+    &mp_type_KeyError,
+    &mp_type_IndexError,
+    &mp_type_AttributeError,
+    &mp_type_OverflowError,
+    &mp_type_ZeroDivisionError,
+    &mp_type_NotImplementedError,
+    &mp_type_OSError,
+    &mp_type_MemoryError,
+};
+
+mp_obj_t hl_exception_new(size_t type, const char *msg, size_t len) {
+    nlr_buf_t nlr;
+    if (nlr_push(&nlr) == 0) {
+        if (type >= MP_ARRAY_SIZE(hl_exception_types)) {
+            type = 0;
+        }
+        mp_obj_t exc = mp_obj_new_exception_arg1(hl_exception_types[type], mp_obj_new_str(msg, len));
+        nlr_pop();
+        return exc;
+    } else {
+        return MP_OBJ_FROM_PTR(nlr.ret_val);
+    }
+}
+
+mp_obj_t hl_obj_none(void) {
+    return mp_const_none;
+}
+
+mp_obj_t hl_obj_bool(bool value) {
+    return mp_obj_new_bool(value);
+}
+
+const char *hl_obj_type_name(mp_obj_t obj) {
+    return mp_obj_get_type_str(obj);
+}
+
+int hl_obj_new_int(int64_t value, mp_obj_t *out) {
+    HL_TRY
+    *out = mp_obj_new_int_from_ll(value);
+    HL_CATCH(out)
+}
+
+int hl_obj_new_float(double value, mp_obj_t *out) {
+    HL_TRY
+    *out = mp_obj_new_float((mp_float_t)value);
+    HL_CATCH(out)
+}
+
+int hl_obj_new_str(const char *data, size_t len, mp_obj_t *out) {
+    HL_TRY
+    *out = mp_obj_new_str(data, len);
+    HL_CATCH(out)
+}
+
+int hl_obj_new_bytes(const uint8_t *data, size_t len, mp_obj_t *out) {
+    HL_TRY
+    *out = mp_obj_new_bytes(data, len);
+    HL_CATCH(out)
+}
+
+int hl_obj_new_tuple(const mp_obj_t *items, size_t len, mp_obj_t *out) {
+    HL_TRY
+    *out = mp_obj_new_tuple(len, items);
+    HL_CATCH(out)
+}
+
+int hl_obj_new_list(const mp_obj_t *items, size_t len, mp_obj_t *out) {
+    HL_TRY
+    // mp_obj_new_list takes the items as non-const, it only copies them
+    *out = mp_obj_new_list(len, (mp_obj_t *)items);
+    HL_CATCH(out)
+}
+
+int hl_obj_is_true(mp_obj_t obj, bool *value, mp_obj_t *exc) {
+    HL_TRY
+    *value = mp_obj_is_true(obj);
+    HL_CATCH(exc)
+}
+
+int hl_obj_get_int(mp_obj_t obj, int64_t *value, mp_obj_t *exc) {
+    HL_TRY
+    *value = mp_obj_get_int(obj);
+    HL_CATCH(exc)
+}
+
+int hl_obj_get_float(mp_obj_t obj, double *value, mp_obj_t *exc) {
+    HL_TRY
+    *value = mp_obj_get_float(obj);
+    HL_CATCH(exc)
+}
+
+int hl_obj_get_str(mp_obj_t obj, const char **data, size_t *len, mp_obj_t *exc) {
+    HL_TRY
+    if (!mp_obj_is_str(obj)) {
+        mp_raise_msg_varg(&mp_type_TypeError,
+            MP_ERROR_TEXT("expected str, got '%s'"), mp_obj_get_type_str(obj));
+    }
+    *data = mp_obj_str_get_data(obj, len);
+    HL_CATCH(exc)
+}
+
+int hl_obj_get_buffer(mp_obj_t obj, const uint8_t **data, size_t *len, mp_obj_t *exc) {
+    HL_TRY
+    mp_buffer_info_t bufinfo;
+    mp_get_buffer_raise(obj, &bufinfo, MP_BUFFER_READ);
+    *data = bufinfo.buf;
+    *len = bufinfo.len;
+    HL_CATCH(exc)
+}
+
+int hl_obj_new_dict(mp_obj_t *out) {
+    HL_TRY
+    *out = mp_obj_new_dict(0);
+    HL_CATCH(out)
+}
+
+int hl_obj_str(mp_obj_t obj, bool repr, mp_obj_t *out) {
+    HL_TRY
+    vstr_t vstr;
+    mp_print_t print;
+    vstr_init_print(&vstr, 16, &print);
+    mp_obj_print_helper(&print, obj, repr ? PRINT_REPR : PRINT_STR);
+    *out = mp_obj_new_str_from_vstr(&vstr);
+    HL_CATCH(out)
+}
+
+int hl_obj_len(mp_obj_t obj, size_t *len, mp_obj_t *exc) {
+    HL_TRY
+    *len = mp_obj_get_int(mp_obj_len(obj));
+    HL_CATCH(exc)
+}
+
+int hl_obj_get_array(mp_obj_t obj, mp_obj_t **items, size_t *len, mp_obj_t *exc) {
+    HL_TRY
+    mp_obj_get_array(obj, len, items);
+    HL_CATCH(exc)
+}
+
+int hl_obj_list_append(mp_obj_t list, mp_obj_t item, mp_obj_t *exc) {
+    HL_TRY
+    if (!mp_obj_is_type(list, &mp_type_list)) {
+        mp_raise_msg_varg(&mp_type_TypeError,
+            MP_ERROR_TEXT("expected list, got '%s'"), mp_obj_get_type_str(list));
+    }
+    mp_obj_list_append(list, item);
+    HL_CATCH(exc)
+}
+
+int hl_obj_get_item(mp_obj_t obj, mp_obj_t key, mp_obj_t *out) {
+    HL_TRY
+    *out = mp_obj_subscr(obj, key, MP_OBJ_SENTINEL);
+    HL_CATCH(out)
+}
+
+int hl_obj_set_item(mp_obj_t obj, mp_obj_t key, mp_obj_t value, mp_obj_t *exc) {
+    HL_TRY
+    mp_obj_subscr(obj, key, value);
+    HL_CATCH(exc)
+}
+
+int hl_obj_load_attr(mp_obj_t obj, const char *name, size_t len, mp_obj_t *out) {
+    HL_TRY
+    *out = mp_load_attr(obj, qstr_from_strn(name, len));
+    HL_CATCH(out)
+}
+
+int hl_obj_store_attr(mp_obj_t obj, const char *name, size_t len, mp_obj_t value, mp_obj_t *exc) {
+    HL_TRY
+    mp_store_attr(obj, qstr_from_strn(name, len), value);
+    HL_CATCH(exc)
+}
+
+int hl_obj_call(mp_obj_t fun, const mp_obj_t *args, size_t n_args, mp_obj_t *out) {
+    HL_TRY
+    *out = mp_call_function_n_kw(fun, n_args, 0, args);
+    HL_CATCH(out)
+}
+
+int hl_obj_getiter(mp_obj_t obj, mp_obj_t *out) {
+    HL_TRY
+    *out = mp_getiter(obj, NULL);
+    HL_CATCH(out)
+}
+
+int hl_obj_iternext(mp_obj_t iter, mp_obj_t *out) {
+    HL_TRY
+    *out = mp_iternext(iter);
+    if (*out == MP_OBJ_STOP_ITERATION) {
+        *out = MP_OBJ_NULL;
+    }
+    HL_CATCH(out)
+}
+
+int hl_obj_import(const char *name, size_t len, mp_obj_t *out) {
+    HL_TRY
+    // Goes through the import hook, so the module policy applies
+    *out = mp_import_name(qstr_from_strn(name, len), mp_const_none, MP_OBJ_NEW_SMALL_INT(0));
+    HL_CATCH(out)
+}
+
+int hl_nlr_call(void (*fun)(void *), void *data, mp_obj_t *exc) {
+    HL_TRY
+    fun(data);
+    HL_CATCH(exc)
+}
+
+// Objects kept alive by Rust code, NULL for the free slots.
+// The GC heap doesn't see this static array, hl_gc_collect scans it.
+static mp_obj_t hl_gc_roots[HL_GC_ROOTS_MAX];
+
+int hl_gc_root_add(mp_obj_t obj, size_t *slot) {
+    for (size_t i = 0; i < HL_GC_ROOTS_MAX; i++) {
+        if (hl_gc_roots[i] == MP_OBJ_NULL) {
+            hl_gc_roots[i] = obj;
+            *slot = i;
+            return 0;
+        }
+    }
+    return 1;
+}
+
+void hl_gc_root_remove(size_t slot) {
+    if (slot < HL_GC_ROOTS_MAX) {
+        hl_gc_roots[slot] = MP_OBJ_NULL;
+    }
+}
+
+// Table of the native modules, a dict from their names to the modules, alongside
+// MicroPython's built-in module table which is in ROM. Created by the first
+// hl_module_new call, scanned by hl_gc_collect.
+static mp_obj_t hl_native_modules = MP_OBJ_NULL;
+
+void hl_gc_collect(void) {
+    gc_collect_start();
+    gc_helper_collect_regs_and_stack();
+    gc_collect_root((void **)hl_gc_roots, HL_GC_ROOTS_MAX);
+    gc_collect_root((void **)&hl_native_modules, 1);
+    gc_collect_end();
+}
+
+// A Python function implemented in Rust
+typedef struct _hl_native_fun_obj_t {
+    mp_obj_base_t base;
+    qstr name;
+    hl_native_fun_t fun;
+    const void *data;
+} hl_native_fun_obj_t;
+
+static mp_obj_t hl_native_fun_call(mp_obj_t self_in, size_t n_args, size_t n_kw, const mp_obj_t *args) {
+    hl_native_fun_obj_t *self = MP_OBJ_TO_PTR(self_in);
+
+    if (n_kw != 0) {
+        mp_raise_msg_varg(&mp_type_TypeError,
+            MP_ERROR_TEXT("%q() takes no keyword arguments"), self->name);
+    }
+
+    mp_obj_t result = MP_OBJ_NULL;
+    if (self->fun(self->data, n_args, args, &result) != 0) {
+        nlr_raise(result);
+    }
+    return result;
+}
+
+static void hl_native_fun_print(const mp_print_t *print, mp_obj_t self_in, mp_print_kind_t kind) {
+    (void)kind;
+    hl_native_fun_obj_t *self = MP_OBJ_TO_PTR(self_in);
+
+    mp_printf(print, "<function %q>", self->name);
+}
+
+static MP_DEFINE_CONST_OBJ_TYPE(
+    hl_type_native_fun,
+    MP_QSTR_function,
+    MP_TYPE_FLAG_NONE,
+    call, hl_native_fun_call,
+    print, hl_native_fun_print
+    );
+
+int hl_native_fun_new(const char *name, size_t len, hl_native_fun_t fun, const void *data, mp_obj_t *out) {
+    HL_TRY
+    hl_native_fun_obj_t *self = mp_obj_malloc(hl_native_fun_obj_t, &hl_type_native_fun);
+    self->name = qstr_from_strn(name, len);
+    self->fun = fun;
+    self->data = data;
+    *out = MP_OBJ_FROM_PTR(self);
+    HL_CATCH(out)
+}
+
+int hl_module_new(const char *name, size_t len, mp_obj_t *out) {
+    HL_TRY
+    qstr qname = qstr_from_strn(name, len);
+    if (hl_native_modules == MP_OBJ_NULL) {
+        hl_native_modules = mp_obj_new_dict(0);
+    }
+
+    // Not mp_obj_new_module, which adds the module to the loaded modules:
+    // like a built-in module, it is only found there once imported
+    mp_obj_module_t *module = mp_obj_malloc(mp_obj_module_t, &mp_type_module);
+    module->globals = MP_OBJ_TO_PTR(mp_obj_new_dict(MICROPY_MODULE_DICT_SIZE));
+    mp_obj_dict_store(MP_OBJ_FROM_PTR(module->globals),
+        MP_OBJ_NEW_QSTR(MP_QSTR___name__), MP_OBJ_NEW_QSTR(qname));
+    mp_obj_dict_store(hl_native_modules, MP_OBJ_NEW_QSTR(qname), MP_OBJ_FROM_PTR(module));
+
+    *out = MP_OBJ_FROM_PTR(module);
+    HL_CATCH(out)
+}
+
+// The native module imported by name, NULL if there is none
+static mp_obj_t hl_native_module_get(qstr name) {
+    if (hl_native_modules == MP_OBJ_NULL) {
+        return MP_OBJ_NULL;
+    }
+
+    mp_map_elem_t *elem = mp_map_lookup(mp_obj_dict_get_map(hl_native_modules),
+        MP_OBJ_NEW_QSTR(name), MP_MAP_LOOKUP);
+    return elem == NULL ? MP_OBJ_NULL : elem->value;
+}
+
+/* ============================================================================
+ * Import hook
+ * ============================================================================
+ */
+
+// How direct calls to __import__ are handled, see hl_set_import_call_policy
+static int hl_import_call_mode = HL_IMPORT_CALL_ALLOWED;
+static qstr hl_import_call_policy = MP_QSTRnull;
+
+// Set by the import statements right before they call __import__, see
+// __wrap_mp_import_name
+static bool hl_import_statement = false;
+
+void hl_set_import_call_policy(int mode, const char *policy, size_t len) {
+    hl_import_call_mode = mode;
+    hl_import_call_policy = qstr_from_strn(policy, len);
+}
+
+mp_obj_t __real_mp_import_name(qstr name, mp_obj_t fromlist, mp_obj_t level);
+
+// Import statements compile to calls to mp_import_name, the guest links with
+// --wrap=mp_import_name so that __import__ can tell them from direct calls.
+mp_obj_t __wrap_mp_import_name(qstr name, mp_obj_t fromlist, mp_obj_t level) {
+    hl_import_statement = true;
+    return __real_mp_import_name(name, fromlist, level);
+}
+
+// Replaces MicroPython's __import__ (see mpconfigport.h) so that the sandbox
+// module policy applies to built-in, frozen and host-registered modules alike,
+// and the builtin policy to the direct calls.
+mp_obj_t hl_builtin___import__(size_t n_args, const mp_obj_t *args) {
+    // Consumed right away: the imported module may call __import__ directly
+    bool statement = hl_import_statement;
+    hl_import_statement = false;
+
+    if (!statement && hl_import_call_mode == HL_IMPORT_CALL_REMOVED) {
+        mp_raise_msg(&mp_type_NameError, MP_ERROR_TEXT("name '__import__' is not defined"));
+    }
+    if (!statement && hl_import_call_mode == HL_IMPORT_CALL_DENIED) {
+        // Defined by the builtin policy prelude, see python-host/src/builtins.rs
+        mp_obj_t permission_error = mp_load_builtin(qstr_from_str("PermissionError"));
+        nlr_raise(mp_obj_new_exception_msg_varg(MP_OBJ_TO_PTR(permission_error),
+            MP_ERROR_TEXT("'__import__' is disabled by the builtin policy '%q'"),
+            hl_import_call_policy));
+    }
+
+    const char *name = mp_obj_str_get_str(args[0]);
+
+    if (!hl_module_allowed(name, strlen(name))) {
+        mp_raise_msg_varg(&mp_type_ImportError,
+            MP_ERROR_TEXT("import of '%s' is blocked by the sandbox module policy"), name);
+    }
+
+    // Native modules are looked up like the built-in ones, before the loaded
+    // modules and the frozen modules. They are top-level modules, so a relative
+    // import never finds them.
+    if (n_args < 5 || MP_OBJ_SMALL_INT_VALUE(args[4]) == 0) {
+        qstr qname = qstr_from_str(name);
+        mp_obj_t native_module = hl_native_module_get(qname);
+        if (native_module != MP_OBJ_NULL) {
+            mp_map_lookup(&MP_STATE_VM(mp_loaded_modules_dict).map, MP_OBJ_NEW_QSTR(qname),
+                MP_MAP_LOOKUP_ADD_IF_NOT_FOUND)->value = native_module;
+            return native_module;
+        }
+    }
+
+    return mp_builtin___import___default(n_args, args);
+}
+
+#if HL_FROZEN
+/* ============================================================================
+ * Frozen modules
+ * ============================================================================
+ */
+
+// External imports are enabled for the frozen modules only, the guest has no
+// filesystem. Weak in case the embed port provides them.
+MP_WEAK mp_import_stat_t mp_import_stat(const char *path) {
+    (void)path;
+    return MP_IMPORT_STAT_NO_EXIST;
+}
+
+MP_WEAK mp_lexer_t *mp_lexer_new_from_file(qstr filename) {
+    (void)filename;
+    mp_raise_OSError(MP_ENOENT);
+}
+#endif
@@ -0,0 +1,39 @@
+/*
+ * Backtracking limit for the re module.
+ *
+ * extmod/modre.c includes this file in place of MicroPython's
+ * lib/re1.5/recursiveloop.c, since its include directory comes first
+ * (see build.rs). The original matcher is included below, with every
+ * backtracking step counted so that a pathological pattern raises a
+ * RuntimeError instead of running for ages.
+ */
+
+// Maximum number of backtracking steps of a single match, 0 for no limit
+// (see hl_re_set_backtrack_limit in micropython_stubs.c)
+extern size_t hl_re_backtrack_limit;
+
+// Backtracking steps taken by the current match
+static size_t hl_re_steps;
+
+// The matcher recurses at every backtracking point and checks the stack first
+static void hl_re_step(void) {
+    MP_STACK_CHECK();
+
+    if (hl_re_backtrack_limit != 0 && ++hl_re_steps > hl_re_backtrack_limit) {
+        mp_raise_msg(&mp_type_RuntimeError, MP_ERROR_TEXT("regex backtrack limit exceeded"));
+    }
+}
+
+#undef re1_5_stack_chk
+#define re1_5_stack_chk() hl_re_step()
+
+// The original matcher is renamed so that every match resets the step counter
+#define re1_5_recursiveloopprog hl_re_recursiveloopprog
+#include_next "lib/re1.5/recursiveloop.c"
+#undef re1_5_recursiveloopprog
+
+int re1_5_recursiveloopprog(ByteProg *prog, Subject *input, const char **subp, int nsub, int is_anchored) {
+    hl_re_steps = 0;
+
+    return hl_re_recursiveloopprog(prog, input, subp, nsub, is_anchored);
+}
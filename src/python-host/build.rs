@@ -1,9 +1,15 @@
 //! Build script for python-host
 //!
 //! The MicroPython compilation is handled by the micropython-lib crate.
-//! This build script is kept minimal.
+//! This build script only sets the link arguments the guest needs.
 
 fn main() {
-    // Nothing to do - micropython-lib handles the C compilation
+    // Route MicroPython's gc_collect through __wrap_gc_collect so that
+    // collections can be counted for the per-run execution stats, and scan
+    // the objects rooted from Rust
+    println!("cargo:rustc-link-arg-bins=--wrap=gc_collect");
+    // Let __import__ tell import statements from direct calls, which the
+    // builtin policy can deny
+    println!("cargo:rustc-link-arg-bins=--wrap=mp_import_name");
     println!("cargo:rerun-if-changed=build.rs");
 }
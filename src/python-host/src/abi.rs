@@ -0,0 +1,50 @@
+//! Version and description of the guest, returned to the host by `abi_info`.
+//!
+//! The host checks them before initializing the runtime, so that a guest binary
+//! built against another version of hyperlight-python fails with a clear error
+//! instead of misbehaving on the first call.
+
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::micropython::{DEFAULT_HEAP_SIZE, STACK_CHECK_MARGIN};
+use crate::native_modules;
+
+/// Version of the interface between the host and the guest: the guest functions, their
+/// arguments and the encoding of their results. Bumped on every incompatible change,
+/// must match `GUEST_ABI_VERSION` of hyperlight-python.
+pub const ABI_VERSION: u32 = 2;
+
+/// The guest description as a JSON object, e.g.
+/// `{"abi_version": 2, "micropython_version": "1.27.0", "features": ["profile-core"],
+/// "native_modules": ["fastmath"], "heap_size": 32768, "stack_check_margin": 24576}`.
+/// The features and module names are identifiers, they need no escaping.
+pub fn info() -> String {
+    let features: Vec<&str> = micropython_lib::FEATURES
+        .split(',')
+        .filter(|feature| !feature.is_empty())
+        .collect();
+
+    format!(
+        "{{\"abi_version\": {}, \"micropython_version\": \"{}.{}.{}\", \"features\": {}, \
+         \"native_modules\": {}, \"heap_size\": {}, \"stack_check_margin\": {}}}",
+        ABI_VERSION,
+        micropython_lib::MICROPY_VERSION_MAJOR,
+        micropython_lib::MICROPY_VERSION_MINOR,
+        micropython_lib::MICROPY_VERSION_MICRO,
+        json_list(&features),
+        json_list(&native_modules::names()),
+        DEFAULT_HEAP_SIZE,
+        STACK_CHECK_MARGIN,
+    )
+}
+
+/// A JSON list of identifiers
+fn json_list(items: &[&str]) -> String {
+    let items: Vec<String> = items.iter().map(|item| format!("\"{}\"", item)).collect();
+
+    format!("[{}]", items.join(", "))
+}
@@ -0,0 +1,125 @@
+//! Builtin policy applied on top of the MicroPython builtins.
+//!
+//! Restricted builtins are replaced in the builtins override dict
+//! (`MICROPY_CAN_OVERRIDE_BUILTINS`) by functions that raise when called.
+//! Denied builtins raise a `PermissionError` naming the policy, while removed
+//! builtins raise the `NameError` CPython raises for an undefined name.
+//! The names still resolve: MicroPython's builtins are in ROM, the override dict
+//! can only shadow them. `hasattr(builtins, name)` would see them, which is why
+//! the `builtins` module is blocked once a policy is applied.
+//!
+//! `__import__` can't be replaced since import statements call the replacement too.
+//! Its direct calls are restricted by the C import hook instead, see
+//! `hl_set_import_call_policy`.
+
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use core::ffi::c_char;
+
+use crate::imports;
+use crate::micropython::MicroPython;
+
+/// Python code defining the helpers used to replace builtins
+const POLICY_PRELUDE: &str = r#"
+import builtins
+class PermissionError(OSError):
+    pass
+builtins.PermissionError = PermissionError
+def _hl_denied(name, policy):
+    def denied(*args, **kwargs):
+        raise PermissionError("'%s' is disabled by the builtin policy '%s'" % (name, policy))
+    return denied
+def _hl_removed(name):
+    def removed(*args, **kwargs):
+        raise NameError("name '%s' is not defined" % name)
+    return removed
+"#;
+
+/// Python code removing the helpers from the global namespace
+const POLICY_EPILOGUE: &str = "del builtins, PermissionError, _hl_denied, _hl_removed\n";
+
+/// Whether `name` is a valid Python identifier
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Quote `value` as a Python string literal
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+
+    quoted.push('\'');
+    for c in value.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '\'' => quoted.push_str("\\'"),
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('\'');
+
+    quoted
+}
+
+/// Apply a builtin policy.
+/// # Arguments
+/// * `runtime` - The initialized MicroPython runtime
+/// * `policy` - Name of the policy, reported by denied builtins
+/// * `denied` - Newline separated builtins that raise a `PermissionError`
+/// * `removed` - Newline separated builtins that raise a `NameError`
+///
+/// Returns false if one of the builtin names is not a valid identifier
+/// or a builtin could not be replaced.
+pub fn apply_policy(runtime: &MicroPython, policy: &str, denied: &str, removed: &str) -> bool {
+    let denied = denied.lines().map(str::trim).filter(|n| !n.is_empty());
+    let removed = removed.lines().map(str::trim).filter(|n| !n.is_empty());
+
+    if !denied.clone().chain(removed.clone()).all(is_identifier) {
+        return false;
+    }
+
+    // Direct calls to __import__ are restricted by the import hook
+    let import_call_mode = if removed.clone().any(|name| name == "__import__") {
+        micropython_lib::HL_IMPORT_CALL_REMOVED
+    } else if denied.clone().any(|name| name == "__import__") {
+        micropython_lib::HL_IMPORT_CALL_DENIED
+    } else {
+        micropython_lib::HL_IMPORT_CALL_ALLOWED
+    };
+    let denied = denied.filter(|name| *name != "__import__");
+    let removed = removed.filter(|name| *name != "__import__");
+
+    let mut code = String::from(POLICY_PRELUDE);
+    let quoted_policy = quote(policy);
+    for name in denied {
+        code.push_str(&format!(
+            "builtins.{name} = _hl_denied('{name}', {quoted_policy})\n"
+        ));
+    }
+    for name in removed {
+        code.push_str(&format!("builtins.{name} = _hl_removed('{name}')\n"));
+    }
+    code.push_str(POLICY_EPILOGUE);
+
+    if !runtime.exec(&code) {
+        return false;
+    }
+
+    unsafe {
+        micropython_lib::hl_set_import_call_policy(
+            import_call_mode as i32,
+            policy.as_ptr() as *const c_char,
+            policy.len(),
+        );
+    }
+
+    // Scripts could otherwise restore the original builtins through the builtins module
+    imports::block("builtins");
+
+    true
+}
@@ -0,0 +1,57 @@
+//! Clock of the MicroPython `time` module.
+//!
+//! `extmod/modtime.c` reads the time and sleeps through the `mp_hal_*` port functions,
+//! implemented in `micropython_stubs.c` on top of the host function calls made here,
+//! so that the clock policy set on the host applies (real, frozen or virtual time).
+//! A failed host call raises an `OSError` into the script.
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use hyperlight_common::flatbuffer_wrappers::function_types::{ParameterValue, ReturnType};
+use hyperlight_guest_bin::host_comm::call_host_function;
+
+use crate::stats;
+
+/// Read a host clock, in nanoseconds, into `out`.
+/// Returns false if the host call failed, e.g. when it diverges from a replayed run.
+fn host_clock(name: &str, out: *mut u64) -> bool {
+    stats::record_host_call();
+
+    match call_host_function::<u64>(name, None, ReturnType::ULong) {
+        Ok(ns) => {
+            unsafe { *out = ns };
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// Nanoseconds since the Unix epoch - called from the `mp_hal_time_ns` C stub,
+/// which raises an `OSError` if it returns false
+#[unsafe(no_mangle)]
+pub extern "C" fn hl_time_ns(out: *mut u64) -> bool {
+    host_clock("HostTimeNs", out)
+}
+
+/// Monotonic nanoseconds - called from the `mp_hal_ticks_*` C stubs,
+/// which raise an `OSError` if it returns false
+#[unsafe(no_mangle)]
+pub extern "C" fn hl_ticks_ns(out: *mut u64) -> bool {
+    host_clock("HostTicksNs", out)
+}
+
+/// Sleep for `ns` nanoseconds on the host clock - called from the `mp_hal_delay_*`
+/// C stubs, which raise an `OSError` if it returns false
+#[unsafe(no_mangle)]
+pub extern "C" fn hl_sleep_ns(ns: u64) -> bool {
+    stats::record_host_call();
+
+    call_host_function::<()>(
+        "HostSleepNs",
+        Some(Vec::from([ParameterValue::ULong(ns)])),
+        ReturnType::Void,
+    )
+    .is_ok()
+}
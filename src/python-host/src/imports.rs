@@ -0,0 +1,98 @@
+//! Import policy applied by the guest import hook.
+//!
+//! MicroPython's `__import__` is replaced by `hl_builtin___import__` (see `mpconfigport.h`),
+//! which asks [`hl_module_allowed`] before importing any module, be it built-in,
+//! frozen or registered by the host.
+
+extern crate alloc;
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::ffi::c_char;
+use spin::Mutex;
+
+/// Import policy of the sandbox
+enum ModulePolicy {
+    /// Every module can be imported
+    Unrestricted,
+    /// Only the listed modules (and their submodules) can be imported
+    Allow(Vec<String>),
+    /// Every module except the listed ones (and their submodules) can be imported
+    Deny(Vec<String>),
+}
+
+/// Policy mode sent by the host for an unrestricted policy
+pub const MODE_UNRESTRICTED: i32 = 0;
+/// Policy mode sent by the host for an allowlist
+pub const MODE_ALLOW: i32 = 1;
+/// Policy mode sent by the host for a denylist
+pub const MODE_DENY: i32 = 2;
+
+/// Current import policy
+static POLICY: Mutex<ModulePolicy> = Mutex::new(ModulePolicy::Unrestricted);
+
+/// Modules blocked by the guest itself, regardless of the import policy
+static BLOCKED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Set the import policy.
+/// # Arguments
+/// * `mode` - One of [`MODE_UNRESTRICTED`], [`MODE_ALLOW`] or [`MODE_DENY`]
+/// * `modules` - Newline separated module names
+///
+/// Returns false if the mode is unknown.
+pub fn set_policy(mode: i32, modules: &str) -> bool {
+    let modules = modules
+        .lines()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(ToString::to_string)
+        .collect();
+
+    let policy = match mode {
+        MODE_UNRESTRICTED => ModulePolicy::Unrestricted,
+        MODE_ALLOW => ModulePolicy::Allow(modules),
+        MODE_DENY => ModulePolicy::Deny(modules),
+        _ => return false,
+    };
+
+    *POLICY.lock() = policy;
+    true
+}
+
+/// Block a module regardless of the import policy.
+/// Used by other sandbox policies that a module would allow to bypass.
+pub fn block(module: &str) {
+    let mut blocked = BLOCKED.lock();
+
+    if !blocked.iter().any(|name| name == module) {
+        blocked.push(module.to_string());
+    }
+}
+
+/// Whether `name` is one of `modules` or a submodule of one of them
+fn matches(modules: &[String], name: &str) -> bool {
+    modules.iter().any(|module| {
+        name == module
+            || (name.starts_with(module.as_str())
+                && name.as_bytes().get(module.len()) == Some(&b'.'))
+    })
+}
+
+/// Check whether a module may be imported - called from the C import hook
+#[unsafe(no_mangle)]
+pub extern "C" fn hl_module_allowed(name: *const c_char, len: usize) -> bool {
+    let bytes = unsafe { core::slice::from_raw_parts(name as *const u8, len) };
+    let Ok(name) = core::str::from_utf8(bytes) else {
+        return false;
+    };
+
+    if matches(&BLOCKED.lock(), name) {
+        return false;
+    }
+
+    match &*POLICY.lock() {
+        ModulePolicy::Unrestricted => true,
+        ModulePolicy::Allow(modules) => matches(modules, name),
+        ModulePolicy::Deny(modules) => !matches(modules, name),
+    }
+}
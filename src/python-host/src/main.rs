@@ -8,16 +8,35 @@
 
 extern crate alloc;
 
+/// Version and description of the guest checked by the host
+mod abi;
+/// Builtin policy applied on top of the MicroPython builtins
+mod builtins;
+/// Host clock of the `time` module
+mod clock;
+/// Import policy enforced by the guest import hook
+mod imports;
 /// MicroPython runtime module
 mod micropython;
+/// Python modules implemented in Rust
+mod native_modules;
+/// Script output and its size limit
+mod output;
+/// Host entropy seeding the `random` module
+mod random;
+/// Per-run resource usage counters
+mod stats;
+/// Values returned to the host
+mod values;
 
+use alloc::format;
 use alloc::string::String;
 use alloc::vec::Vec;
 use core::ffi::c_char;
 use hyperlight_common::flatbuffer_wrappers::function_call::FunctionCall;
 use hyperlight_common::flatbuffer_wrappers::function_types::{ParameterValue, ReturnType};
-use hyperlight_common::flatbuffer_wrappers::util::get_flatbuffer_result;
-use hyperlight_guest::error::Result;
+use hyperlight_common::flatbuffer_wrappers::guest_error::ErrorCode;
+use hyperlight_guest::error::{HyperlightGuestError, Result};
 use hyperlight_guest_bin::guest_function;
 use hyperlight_guest_bin::host_comm::call_host_function;
 
@@ -31,7 +50,9 @@ static MP_RUNTIME: spin::Once<MicroPython> = spin::Once::new();
 /// * `msg` - The message to print
 /// # Returns
 /// * `Result<i32>` - The result of the host print call
-fn host_print(msg: &str) -> Result<i32> {
+pub(crate) fn host_print(msg: &str) -> Result<i32> {
+    stats::record_host_call();
+
     call_host_function::<i32>(
         "HostPrint",
         Some(Vec::from([ParameterValue::String(String::from(msg))])),
@@ -39,53 +60,194 @@ fn host_print(msg: &str) -> Result<i32> {
     )
 }
 
-/// Print a single character - called from C stubs
+/// Print a single character - called from C stubs.
+/// Subject to the output limit, like all Python output.
 #[unsafe(no_mangle)]
 pub extern "C" fn hl_print_char(c: c_char) {
-    let ch = c as u8;
-
-    let _ = host_print(core::str::from_utf8(&[ch]).unwrap_or("?"));
+    output::hl_output_write(&c, 1);
 }
 
-/// Print a null-terminated string - called from C stubs
+/// Print a null-terminated string - called from C stubs.
+/// Subject to the output limit, like all Python output.
 #[unsafe(no_mangle)]
 pub extern "C" fn hl_print_str(s: *const c_char) {
-    let _ = host_print(unsafe {
-        core::ffi::CStr::from_ptr(s)
-            .to_str()
-            .unwrap_or("Invalid UTF-8 string")
-    });
+    let len = unsafe { core::ffi::CStr::from_ptr(s) }.count_bytes();
+
+    output::hl_output_write(s, len);
+}
+
+/// Wrapper around MicroPython's `gc_collect` that keeps track of collections
+/// and of the heap usage right before each one.
+/// Collects with [`micropython_lib::gc_collect`], which also scans the objects
+/// rooted from Rust.
+#[unsafe(no_mangle)]
+pub extern "C" fn __wrap_gc_collect() {
+    stats::record_heap_usage();
+
+    micropython_lib::gc_collect();
+
+    stats::record_gc_collection();
 }
 
 /// Initialize the MicroPython runtime.
 /// This must be called before exec_python.
-/// Returns "OK" on success or an error message.
+/// `stack_size` is the size of the guest stack, used to detect runaway recursion.
+/// Returns true on success.
 #[guest_function("init_python")]
-fn init_python() -> bool {
+fn init_python(stack_size: u64) -> bool {
     if let Some(_) = MP_RUNTIME.get() {
         true
     } else {
-        let runtime = MicroPython::init();
+        let runtime = MicroPython::init(stack_size as usize);
 
         runtime.map(|rt| MP_RUNTIME.call_once(|| rt)).is_ok()
     }
 }
 
+/// Run `f` with the MicroPython runtime, accounting for it as a run
+/// in the execution stats and the output limit.
+/// Returns None if the runtime is not initialized.
+fn run<T>(f: impl FnOnce(&MicroPython) -> T) -> Option<T> {
+    MP_RUNTIME.get().map(|mp_runtime| {
+        micropython::set_stack_limit();
+        stats::begin_run();
+        output::begin_run();
+
+        let result = f(mp_runtime);
+
+        output::end_run();
+        stats::end_run();
+
+        result
+    })
+}
+
 /// Execute Python code passed as a string.
 /// init_python must be called first.
-/// Returns 0 on success or -1.
+/// Returns false if the runtime is not initialized or the code raised an exception.
 #[guest_function("exec_python")]
 fn exec_python(code: String) -> bool {
+    run(|mp_runtime| mp_runtime.exec(&code)).unwrap_or(false)
+}
+
+/// Evaluate a Python expression passed as a string.
+/// Returns a status byte (0 on success, 1 if an exception was raised) followed by
+/// the encoded value or the exception text, or nothing if the runtime is not initialized.
+#[guest_function("eval_python")]
+fn eval_python(code: String) -> Vec<u8> {
+    run(|mp_runtime| values::response(mp_runtime.eval(&code))).unwrap_or_default()
+}
+
+/// Call the Python function `name` with the encoded tuple of arguments `args`.
+/// Returns a status byte (0 on success, 1 if an exception was raised) followed by
+/// the encoded return value or the exception text, or nothing if the runtime is not initialized.
+#[guest_function("call_python")]
+fn call_python(name: String, args: Vec<u8>) -> Vec<u8> {
+    run(|mp_runtime| values::response(mp_runtime.call(&name, &args))).unwrap_or_default()
+}
+
+/// Decode the JSON document `json` in the guest and store it as the global `name`.
+/// Returns a status byte (0 on success, 1 if an exception was raised) followed by
+/// the encoded `None` or the exception text, or nothing if the runtime is not initialized.
+#[guest_function("set_global_json")]
+fn set_global_json(name: String, json: String) -> Vec<u8> {
+    run(|mp_runtime| values::response(mp_runtime.set_global_json(&name, &json)))
+        .unwrap_or_default()
+}
+
+/// Evaluate a Python expression passed as a string and encode its value as JSON.
+/// Returns a status byte (0 on success, 1 if an exception was raised) followed by
+/// the encoded JSON string or the exception text, or nothing if the runtime is not initialized.
+#[guest_function("eval_json")]
+fn eval_json(code: String) -> Vec<u8> {
+    run(|mp_runtime| values::response(mp_runtime.eval_json(&code))).unwrap_or_default()
+}
+
+/// Return the text of the exception raised by the last exec_python call,
+/// or an empty string if it didn't raise or the runtime is not initialized.
+#[guest_function("last_exception")]
+fn last_exception() -> String {
+    MP_RUNTIME
+        .get()
+        .map(|mp_runtime| mp_runtime.last_exception())
+        .unwrap_or_default()
+}
+
+/// Limit the output of the next exec_python call to `max_bytes`.
+/// `policy` is 0 to truncate and continue, 1 to truncate and abort the script
+/// and 2 to abort the script with an error.
+/// Returns false if the policy is unknown.
+#[guest_function("set_output_limit")]
+fn set_output_limit(max_bytes: u64, policy: i32) -> bool {
+    output::set_limit(max_bytes, policy)
+}
+
+/// Set the import policy of the sandbox.
+/// `mode` is 0 for unrestricted, 1 for an allowlist and 2 for a denylist
+/// of the newline separated `modules`.
+/// Returns false if the mode is unknown.
+#[guest_function("set_module_policy")]
+fn set_module_policy(mode: i32, modules: String) -> bool {
+    imports::set_policy(mode, &modules)
+}
+
+/// Restrict builtins according to the host builtin policy.
+/// `denied` and `removed` are newline separated builtin names.
+/// Returns false if the runtime is not initialized or a name is invalid.
+#[guest_function("set_builtin_policy")]
+fn set_builtin_policy(policy: String, denied: String, removed: String) -> bool {
+    MP_RUNTIME
+        .get()
+        .is_some_and(|mp_runtime| builtins::apply_policy(mp_runtime, &policy, &denied, &removed))
+}
+
+/// Limit the backtracking steps of a single regular expression match to `limit`,
+/// 0 for no limit.
+/// Returns false if the runtime is not initialized.
+#[guest_function("set_regex_backtrack_limit")]
+fn set_regex_backtrack_limit(limit: u64) -> bool {
     MP_RUNTIME
         .get()
-        .map(|mp_runtime| mp_runtime.exec(&code))
+        .map(|mp_runtime| mp_runtime.set_regex_backtrack_limit(limit as usize))
         .is_some()
 }
 
+/// Seed the `random` module with `seed` for the next runs.
+/// Returns false if the runtime is not initialized or seeding raised an exception.
+#[guest_function("seed_random")]
+fn seed_random(seed: u64) -> bool {
+    MP_RUNTIME
+        .get()
+        .is_some_and(|mp_runtime| mp_runtime.seed_random(seed))
+}
+
+/// Return the resource usage counters of the last exec_python call.
+#[guest_function("exec_stats")]
+fn exec_stats() -> Vec<u8> {
+    stats::encode()
+}
+
+/// Return the ABI version, MicroPython version, features and heap configuration of the
+/// guest as JSON, checked by the host before initializing the runtime.
+/// Keep it callable without the runtime, so that any host can read it.
+#[guest_function("abi_info")]
+fn abi_info() -> String {
+    abi::info()
+}
+
 #[unsafe(no_mangle)]
-pub extern "C" fn hyperlight_main() {}
+pub extern "C" fn hyperlight_main() {
+    micropython::record_stack_top();
+}
 
 #[unsafe(no_mangle)]
-pub fn guest_dispatch_function(_function_call: FunctionCall) -> Result<Vec<u8>> {
-    Ok(get_flatbuffer_result(0))
+pub fn guest_dispatch_function(function_call: FunctionCall) -> Result<Vec<u8>> {
+    Err(HyperlightGuestError::new(
+        ErrorCode::GuestFunctionNotFound,
+        format!(
+            "Unknown guest function {} (guest ABI version {})",
+            function_call.function_name,
+            abi::ABI_VERSION
+        ),
+    ))
 }
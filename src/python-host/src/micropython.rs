@@ -9,13 +9,32 @@ use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use core::ffi::c_void;
 use core::sync::atomic::AtomicBool;
+use core::sync::atomic::AtomicUsize;
 use core::sync::atomic::Ordering;
 use hyperlight_guest::error::{HyperlightGuestError, Result};
 use spin::Mutex;
 
+use crate::native_modules;
+
 /// Size of the MicroPython garbage collector heap (32 KB)
 pub const DEFAULT_HEAP_SIZE: usize = 32 * 1024;
 
+/// Stack space kept free below MicroPython's stack limit for the code that runs
+/// after a recursion error is raised (exception unwinding, traceback printing
+/// and the host calls made to print it).
+pub const STACK_CHECK_MARGIN: usize = 24 * 1024;
+
+/// Stack space reserved above the recorded stack top for the frames of the Hyperlight
+/// entrypoint that calls `hyperlight_main`, which the guest can't measure
+const ENTRY_FRAMES_RESERVE: usize = 4096;
+
+/// Stack pointer recorded when the guest starts
+static STACK_TOP: AtomicUsize = AtomicUsize::new(0);
+
+/// Lowest usable address of the guest stack, derived from [`STACK_TOP`] and the
+/// stack size when the runtime is initialized
+static STACK_BOTTOM: AtomicUsize = AtomicUsize::new(0);
+
 /// Static heap for MicroPython's garbage collector.
 /// This needs to be static so it lives for the duration of the program.
 static MP_HEAP: spin::Once<Mutex<Vec<u8>>> = spin::Once::new();
@@ -24,6 +43,68 @@ static MP_HEAP: spin::Once<Mutex<Vec<u8>>> = spin::Once::new();
 /// the stack is only used by one
 static MP_INITIALIZED: Mutex<AtomicBool> = Mutex::new(AtomicBool::new(false));
 
+/// Number of bytes currently in use on the MicroPython GC heap.
+/// Must only be called once the runtime is initialized.
+pub fn heap_used() -> usize {
+    let mut info = micropython_lib::gc_info_t::default();
+
+    unsafe {
+        micropython_lib::gc_info(&mut info);
+    }
+
+    info.used
+}
+
+/// Total number of bytes allocated by the runtime since it was initialized.
+pub fn total_bytes_allocated() -> usize {
+    unsafe { micropython_lib::m_get_total_bytes_allocated() }
+}
+
+/// Current stack pointer
+#[inline(always)]
+fn stack_pointer() -> usize {
+    let sp: usize;
+
+    unsafe {
+        core::arch::asm!("mov {}, rsp", out(reg) sp, options(nomem, nostack, preserves_flags));
+    }
+
+    sp
+}
+
+/// Record the stack pointer the guest stack is measured from.
+/// Must be called as early as possible, i.e. from `hyperlight_main`.
+/// Hyperlight doesn't tell the guest where its stack starts: the frames above this
+/// point are covered by [`ENTRY_FRAMES_RESERVE`] rather than guessed from the address.
+pub fn record_stack_top() {
+    STACK_TOP.store(stack_pointer(), Ordering::Release);
+}
+
+/// Measure MicroPython's stack usage from the current stack pointer, and limit it
+/// to the stack left below it minus [`STACK_CHECK_MARGIN`].
+/// Called at each guest function dispatch rather than once at init, since the
+/// dispatch frames are not guaranteed to sit where `hyperlight_main` ran.
+/// Never limits the usage below half the stack left, so that a small stack still
+/// runs scripts.
+#[inline(always)]
+pub fn set_stack_limit() {
+    let bottom = STACK_BOTTOM.load(Ordering::Acquire);
+    if bottom == 0 {
+        return;
+    }
+
+    let sp = stack_pointer();
+    let available = sp.saturating_sub(bottom);
+    let limit = available
+        .saturating_sub(STACK_CHECK_MARGIN)
+        .max(available / 2);
+
+    unsafe {
+        micropython_lib::mp_stack_set_top(sp as *mut c_void);
+        micropython_lib::mp_stack_set_limit(limit as _);
+    }
+}
+
 /// Represents an initialized MicroPython runtime.
 ///
 /// This struct ensures proper initialization and cleanup of the MicroPython
@@ -39,9 +120,13 @@ impl MicroPython {
     /// This function is safe to call, but only one MicroPython instance
     /// should exist at a time. The runtime uses global state internally.
     ///
+    /// # Arguments
+    /// * `stack_size` - Size of the guest stack in bytes, used to compute the
+    ///   limit at which runaway recursion raises a `RuntimeError`
+    ///
     /// # Returns
     /// A `MicroPython` instance that will deinitialize the runtime when dropped.
-    pub fn init() -> Result<Self> {
+    pub fn init(stack_size: usize) -> Result<Self> {
         // Fail early if runtime already initialized
         if let Some(guard) = MP_INITIALIZED.try_lock() {
             if guard.load(Ordering::Acquire) {
@@ -63,13 +148,27 @@ impl MicroPython {
             let heap_ptr = guard.as_mut_ptr() as *mut c_void;
             let heap_size = guard.capacity();
 
-            // Use the address of a local variable as stack top estimate
-            let mut stack_marker: usize = 0;
-            let stack_ptr = &mut stack_marker as *mut usize as *mut c_void;
+            let stack_top = STACK_TOP.load(Ordering::Acquire);
+            if stack_top == 0 {
+                return Err(HyperlightGuestError::new(
+                    hyperlight_common::flatbuffer_wrappers::guest_error::ErrorCode::GuestError,
+                    "Stack top not recorded".to_string(),
+                ));
+            }
+            let stack_bottom = (stack_top + ENTRY_FRAMES_RESERVE).saturating_sub(stack_size);
+            STACK_BOTTOM.store(stack_bottom, Ordering::Release);
 
             unsafe {
-                micropython_lib::mp_embed_init(heap_ptr, heap_size, stack_ptr);
+                micropython_lib::mp_embed_init(heap_ptr, heap_size, stack_top as *mut c_void);
             }
+            set_stack_limit();
+
+            native_modules::register().map_err(|_| {
+                HyperlightGuestError::new(
+                    hyperlight_common::flatbuffer_wrappers::guest_error::ErrorCode::GuestError,
+                    "Cannot register the native Python modules".to_string(),
+                )
+            })?;
 
             // Mark as initialized
             if let Some(guard) = MP_INITIALIZED.try_lock() {
@@ -90,10 +189,13 @@ impl MicroPython {
     /// # Arguments
     /// * `code` - A string slice containing Python source code to execute.
     ///
+    /// # Returns
+    /// `true` if the code ran to completion, `false` if it raised an exception.
+    ///
     /// # Note
     /// Any output from the Python code (via `print()`) will be sent through
     /// the Hyperlight host call mechanism.
-    pub fn exec(&self, code: &str) {
+    pub fn exec(&self, code: &str) -> bool {
         // We need to ensure the string is null-terminated for C
         // Since we're in no_std, we'll use a stack buffer
         let mut buf = String::with_capacity(code.len() + 1);
@@ -101,11 +203,128 @@ impl MicroPython {
         buf.push_str(code);
         buf.push('\0');
 
+        unsafe { micropython_lib::hl_exec_str(buf.as_ptr() as *const core::ffi::c_char) == 0 }
+    }
+
+    /// Evaluate a Python expression.
+    /// The encoded value, or the text of the exception raised, is stored
+    /// for [`values::response`](crate::values::response).
+    ///
+    /// # Arguments
+    /// * `code` - A string slice containing a Python expression.
+    ///
+    /// # Returns
+    /// `true` if the expression was evaluated, `false` if it raised an exception.
+    pub fn eval(&self, code: &str) -> bool {
+        let mut buf = String::with_capacity(code.len() + 1);
+
+        buf.push_str(code);
+        buf.push('\0');
+
+        unsafe { micropython_lib::hl_eval_str(buf.as_ptr() as *const core::ffi::c_char) == 0 }
+    }
+
+    /// Call a function defined in the `__main__` module.
+    /// The encoded return value, or the text of the exception raised, is stored
+    /// for [`values::response`](crate::values::response).
+    ///
+    /// # Arguments
+    /// * `name` - Name of the function.
+    /// * `args` - The encoded tuple of arguments.
+    ///
+    /// # Returns
+    /// `true` if the function returned, `false` if it raised an exception.
+    pub fn call(&self, name: &str, args: &[u8]) -> bool {
+        let mut buf = String::with_capacity(name.len() + 1);
+
+        buf.push_str(name);
+        buf.push('\0');
+
         unsafe {
-            micropython_lib::mp_embed_exec_str(buf.as_ptr() as *const core::ffi::c_char);
+            micropython_lib::hl_call_function(
+                buf.as_ptr() as *const core::ffi::c_char,
+                args.as_ptr(),
+                args.len(),
+            ) == 0
         }
     }
 
+    /// Decode a JSON document with `json.loads` and store it as a global
+    /// of the `__main__` module.
+    /// The encoded `None`, or the text of the exception raised, is stored
+    /// for [`values::response`](crate::values::response).
+    ///
+    /// # Arguments
+    /// * `name` - Name of the global.
+    /// * `json` - The JSON document.
+    ///
+    /// # Returns
+    /// `true` if the global was set, `false` if the document couldn't be decoded.
+    pub fn set_global_json(&self, name: &str, json: &str) -> bool {
+        let mut buf = String::with_capacity(name.len() + 1);
+
+        buf.push_str(name);
+        buf.push('\0');
+
+        unsafe {
+            micropython_lib::hl_set_global_json(
+                buf.as_ptr() as *const core::ffi::c_char,
+                json.as_ptr() as *const core::ffi::c_char,
+                json.len(),
+            ) == 0
+        }
+    }
+
+    /// Evaluate a Python expression and encode its value with `json.dumps`.
+    /// The encoded JSON string, or the text of the exception raised, is stored
+    /// for [`values::response`](crate::values::response).
+    ///
+    /// # Arguments
+    /// * `code` - A string slice containing a Python expression.
+    ///
+    /// # Returns
+    /// `true` if the value was encoded, `false` if an exception was raised.
+    pub fn eval_json(&self, code: &str) -> bool {
+        let mut buf = String::with_capacity(code.len() + 1);
+
+        buf.push_str(code);
+        buf.push('\0');
+
+        unsafe { micropython_lib::hl_eval_json(buf.as_ptr() as *const core::ffi::c_char) == 0 }
+    }
+
+    /// Set the maximum number of backtracking steps of a single regular
+    /// expression match, past which it raises a `RuntimeError`.
+    ///
+    /// # Arguments
+    /// * `limit` - The number of steps, 0 for no limit.
+    pub fn set_regex_backtrack_limit(&self, limit: usize) {
+        unsafe { micropython_lib::hl_re_set_backtrack_limit(limit) }
+    }
+
+    /// Seed the `random` module, whether or not a script imported it yet.
+    ///
+    /// # Arguments
+    /// * `seed` - The seed; the same seed gives the same random numbers.
+    ///
+    /// # Returns
+    /// `true` if the module was seeded, `false` if seeding raised an exception.
+    pub fn seed_random(&self, seed: u64) -> bool {
+        unsafe { micropython_lib::hl_random_seed(seed) == 0 }
+    }
+
+    /// Text of the exception raised by the last `exec`, `eval` or `call`,
+    /// truncated to 1024 bytes, or an empty string if it didn't raise.
+    pub fn last_exception(&self) -> String {
+        let mut len = 0;
+        let text = unsafe {
+            let data = micropython_lib::hl_last_exception(&mut len);
+            core::slice::from_raw_parts(data as *const u8, len)
+        };
+
+        String::from_utf8_lossy(text).into_owned()
+    }
+
     /// Execute a Python source string (static version for longer code).
     ///
     /// # Arguments
@@ -114,10 +333,8 @@ impl MicroPython {
     /// # Safety
     /// The `code` string must be null-terminated.
     #[allow(dead_code)]
-    pub fn exec_cstr(&self, code: *const core::ffi::c_char) {
-        unsafe {
-            micropython_lib::mp_embed_exec_str(code);
-        }
+    pub fn exec_cstr(&self, code: *const core::ffi::c_char) -> bool {
+        unsafe { micropython_lib::hl_exec_str(code) == 0 }
     }
 }
 
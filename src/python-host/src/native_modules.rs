@@ -0,0 +1,91 @@
+//! Python modules implemented in Rust, importable by the scripts.
+//!
+//! The modules returned by [`modules`] are registered when the runtime is initialized,
+//! in the table of native modules that `import` looks up like the built-in modules.
+//! The sandbox module policy applies to them all the same.
+//! See `micropython_lib::native` for writing a module.
+
+extern crate alloc;
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use micropython_lib::native::{Args, NativeModule};
+use micropython_lib::{MpError, MpExcType, MpObj, MpResult};
+
+/// The native modules of the guest. Add a module here to make it importable.
+fn modules() -> Vec<NativeModule> {
+    Vec::from([NativeModule::new("fastmath")
+        .function("clamp", clamp)
+        .function("cumsum", cumsum)
+        .function("histogram", histogram)
+        .function("scale", scale)])
+}
+
+/// `fastmath.clamp(value, low, high)`: `value` limited to the range from `low` to `high`
+fn clamp(args: Args) -> MpResult<MpObj> {
+    args.expect(3, 3)?;
+    let (value, low, high) = (args.int(0)?, args.int(1)?, args.int(2)?);
+    if low > high {
+        return Err(MpError::value_error("low is greater than high"));
+    }
+
+    MpObj::new_int(value.clamp(low, high))
+}
+
+/// `fastmath.cumsum(values)`: list of the running totals of a list or tuple of `int`s
+fn cumsum(args: Args) -> MpResult<MpObj> {
+    args.expect(1, 1)?;
+    let values: Vec<i64> = args.arg(0)?.try_into()?;
+
+    let mut total: i64 = 0;
+    let totals = values
+        .into_iter()
+        .map(|value| {
+            total = total
+                .checked_add(value)
+                .ok_or_else(|| MpError::new(MpExcType::OverflowError, "cumsum overflowed"))?;
+            Ok(total)
+        })
+        .collect::<MpResult<Vec<_>>>()?;
+
+    totals.try_into()
+}
+
+/// `fastmath.histogram(values)`: dict counting how many times each `int` occurs
+fn histogram(args: Args) -> MpResult<MpObj> {
+    args.expect(1, 1)?;
+    let values: Vec<i64> = args.arg(0)?.try_into()?;
+
+    let mut counts: BTreeMap<i64, i64> = BTreeMap::new();
+    for value in values {
+        *counts.entry(value).or_default() += 1;
+    }
+
+    counts.try_into()
+}
+
+/// `fastmath.scale(weights, factor)`: dict of the `float` weights, keyed by `str`,
+/// multiplied by `factor`
+fn scale(args: Args) -> MpResult<MpObj> {
+    args.expect(2, 2)?;
+    let weights: BTreeMap<String, f64> = args.arg(0)?.try_into()?;
+    let factor = args.float(1)?;
+
+    weights
+        .into_iter()
+        .map(|(key, weight)| (key, weight * factor))
+        .collect::<BTreeMap<_, _>>()
+        .try_into()
+}
+
+/// Names of the native modules
+pub fn names() -> Vec<&'static str> {
+    modules().iter().map(NativeModule::name).collect()
+}
+
+/// Register the native modules.
+/// Must be called once, right after the runtime is initialized.
+pub fn register() -> MpResult<()> {
+    modules().iter().try_for_each(NativeModule::register)
+}
@@ -0,0 +1,159 @@
+//! Output of Python scripts and its size limit.
+//!
+//! All Python output reaches [`hl_output_write`] through `hl_stdout_write` in the C stubs,
+//! or through `hl_print_char` and `hl_print_str`.
+//! Output beyond the limit set for the run is dropped here, so it never crosses the
+//! VM boundary, and the status returned tells the C side which exception to raise.
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ffi::c_char;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use spin::Mutex;
+
+use crate::{host_print, stats};
+
+/// Drop the output beyond the limit and let the script continue
+pub const POLICY_TRUNCATE_AND_CONTINUE: i32 = 0;
+/// Drop the output beyond the limit and stop the script with `SystemExit`
+pub const POLICY_TRUNCATE_AND_ABORT: i32 = 1;
+/// Stop the script with a `RuntimeError` and report an error to the host
+pub const POLICY_ERROR: i32 = 2;
+
+/// The output was written (possibly truncated), nothing to raise
+const OUTPUT_OK: i32 = 0;
+/// The limit was reached, raise `SystemExit`
+const OUTPUT_ABORT: i32 = 1;
+/// The limit was reached, raise `RuntimeError`
+const OUTPUT_ERROR: i32 = 2;
+
+/// Output limit of a run
+#[derive(Clone, Copy)]
+struct OutputLimit {
+    /// Maximum number of bytes that can be printed
+    max_bytes: u64,
+    /// What to do once the limit is reached
+    policy: i32,
+}
+
+/// Limit set by the host for the next run
+static PENDING_LIMIT: Mutex<Option<OutputLimit>> = Mutex::new(None);
+/// Limit of the current run
+static ACTIVE_LIMIT: Mutex<Option<OutputLimit>> = Mutex::new(None);
+/// Number of bytes written during the current run
+static WRITTEN: AtomicU64 = AtomicU64::new(0);
+/// Whether output was dropped during the last run
+static TRUNCATED: AtomicBool = AtomicBool::new(false);
+/// Start of a multi-byte character whose other bytes weren't written yet,
+/// e.g. by `hl_print_char` which writes a byte at a time
+static PARTIAL_CHAR: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+
+/// Set the output limit of the next run.
+/// Returns false if the policy is unknown.
+pub fn set_limit(max_bytes: u64, policy: i32) -> bool {
+    if !matches!(
+        policy,
+        POLICY_TRUNCATE_AND_CONTINUE | POLICY_TRUNCATE_AND_ABORT | POLICY_ERROR
+    ) {
+        return false;
+    }
+
+    *PENDING_LIMIT.lock() = Some(OutputLimit { max_bytes, policy });
+    true
+}
+
+/// Activate the limit set for this run, if any.
+pub fn begin_run() {
+    *ACTIVE_LIMIT.lock() = PENDING_LIMIT.lock().take();
+    WRITTEN.store(0, Ordering::Relaxed);
+    TRUNCATED.store(false, Ordering::Relaxed);
+}
+
+/// Deactivate the limit of the run that just ended,
+/// and print what is left of an incomplete character.
+pub fn end_run() {
+    *ACTIVE_LIMIT.lock() = None;
+
+    let partial = core::mem::take(&mut *PARTIAL_CHAR.lock());
+    if !partial.is_empty() && !truncated() {
+        let _ = host_print(&String::from_utf8_lossy(&partial));
+    }
+}
+
+/// Length of the incomplete character at the end of `bytes`, 0 if the last one is complete
+fn incomplete_tail(bytes: &[u8]) -> usize {
+    for (back, &byte) in bytes.iter().rev().take(3).enumerate() {
+        if byte & 0xc0 == 0x80 {
+            continue;
+        }
+
+        let char_len = match byte {
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => 1,
+        };
+        return if char_len > back + 1 { back + 1 } else { 0 };
+    }
+
+    0
+}
+
+/// Print output to the host, holding back a trailing incomplete character
+/// until the rest of its bytes are written
+fn print(bytes: &[u8]) {
+    let mut partial = PARTIAL_CHAR.lock();
+    partial.extend_from_slice(bytes);
+
+    let complete = partial.len() - incomplete_tail(&partial);
+    if complete > 0 {
+        let _ = host_print(&String::from_utf8_lossy(&partial[..complete]));
+        partial.drain(..complete);
+    }
+}
+
+/// Whether output was dropped during the last run
+pub fn truncated() -> bool {
+    TRUNCATED.load(Ordering::Relaxed)
+}
+
+/// Write Python output to the host, enforcing the output limit of the run
+#[unsafe(no_mangle)]
+pub extern "C" fn hl_output_write(s: *const c_char, len: usize) -> i32 {
+    let bytes = unsafe { core::slice::from_raw_parts(s as *const u8, len) };
+    let limit = *ACTIVE_LIMIT.lock();
+
+    let mut allowed = match limit {
+        Some(limit) => {
+            let written = WRITTEN.load(Ordering::Relaxed);
+            limit.max_bytes.saturating_sub(written).min(len as u64) as usize
+        }
+        None => len,
+    };
+
+    // Don't cut a multi-byte character in half: back up to its first byte
+    while allowed < len && allowed > 0 && bytes[allowed] & 0xc0 == 0x80 {
+        allowed -= 1;
+    }
+
+    if allowed > 0 {
+        WRITTEN.fetch_add(allowed as u64, Ordering::Relaxed);
+        stats::record_print(allowed);
+        print(&bytes[..allowed]);
+    }
+
+    match limit {
+        Some(limit) if allowed < len => {
+            TRUNCATED.store(true, Ordering::Relaxed);
+
+            match limit.policy {
+                POLICY_TRUNCATE_AND_ABORT => OUTPUT_ABORT,
+                POLICY_ERROR => OUTPUT_ERROR,
+                _ => OUTPUT_OK,
+            }
+        }
+        _ => OUTPUT_OK,
+    }
+}
@@ -0,0 +1,25 @@
+//! Seed of the MicroPython `random` module.
+//!
+//! `extmod/modrandom.c` seeds itself on its first import through
+//! `MICROPY_PY_RANDOM_SEED_INIT_FUNC`, which reads the host RNG here.
+//! A run can also be given a fixed seed by the host with `seed_random`.
+
+use hyperlight_common::flatbuffer_wrappers::function_types::ReturnType;
+use hyperlight_guest_bin::host_comm::call_host_function;
+
+use crate::stats;
+
+/// Entropy from the host RNG, into `out` - called from the `hl_random_entropy` C stub,
+/// which raises an `OSError` if it returns false
+#[unsafe(no_mangle)]
+pub extern "C" fn hl_host_random_seed(out: *mut u64) -> bool {
+    stats::record_host_call();
+
+    match call_host_function::<u64>("HostRandomSeed", None, ReturnType::ULong) {
+        Ok(seed) => {
+            unsafe { *out = seed };
+            true
+        }
+        Err(_) => false,
+    }
+}
@@ -0,0 +1,89 @@
+//! Resource usage counters for a single `exec_python` call.
+//!
+//! The counters are reset at the start of every run and reported to the host
+//! through the `exec_stats` guest function.
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{micropython, output};
+
+/// Number of calls made to the host during the run
+static HOST_CALLS: AtomicU64 = AtomicU64::new(0);
+/// Number of bytes printed by the script during the run
+static BYTES_PRINTED: AtomicU64 = AtomicU64::new(0);
+/// Number of garbage collections during the run
+static GC_COLLECTIONS: AtomicU64 = AtomicU64::new(0);
+/// Highest GC heap usage observed during the run
+static PEAK_HEAP: AtomicU64 = AtomicU64::new(0);
+/// Total bytes allocated by the runtime when the run started
+static ALLOC_BASELINE: AtomicU64 = AtomicU64::new(0);
+/// Bytes allocated on the GC heap during the run
+static BYTES_ALLOCATED: AtomicU64 = AtomicU64::new(0);
+
+/// Reset all counters at the start of a run.
+pub fn begin_run() {
+    HOST_CALLS.store(0, Ordering::Relaxed);
+    BYTES_PRINTED.store(0, Ordering::Relaxed);
+    GC_COLLECTIONS.store(0, Ordering::Relaxed);
+    BYTES_ALLOCATED.store(0, Ordering::Relaxed);
+    PEAK_HEAP.store(micropython::heap_used() as u64, Ordering::Relaxed);
+    ALLOC_BASELINE.store(
+        micropython::total_bytes_allocated() as u64,
+        Ordering::Relaxed,
+    );
+}
+
+/// Take the final heap measurements at the end of a run.
+pub fn end_run() {
+    record_heap_usage();
+
+    let total = micropython::total_bytes_allocated() as u64;
+    let baseline = ALLOC_BASELINE.load(Ordering::Relaxed);
+    BYTES_ALLOCATED.store(total.saturating_sub(baseline), Ordering::Relaxed);
+}
+
+/// Sample the GC heap usage and keep the highest value seen.
+/// The heap is fullest right before a collection, so this is called from the
+/// collection hook as well as at the end of a run.
+pub fn record_heap_usage() {
+    PEAK_HEAP.fetch_max(micropython::heap_used() as u64, Ordering::Relaxed);
+}
+
+/// Count a call made to the host.
+pub fn record_host_call() {
+    HOST_CALLS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count bytes printed by the script.
+pub fn record_print(len: usize) {
+    BYTES_PRINTED.fetch_add(len as u64, Ordering::Relaxed);
+}
+
+/// Count a garbage collection.
+pub fn record_gc_collection() {
+    GC_COLLECTIONS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Encode the counters as little-endian `u64` values in the order expected by the host:
+/// peak heap, GC collections, bytes allocated, host calls, bytes printed and
+/// whether the output was truncated (0 or 1).
+pub fn encode() -> Vec<u8> {
+    let fields = [
+        &PEAK_HEAP,
+        &GC_COLLECTIONS,
+        &BYTES_ALLOCATED,
+        &HOST_CALLS,
+        &BYTES_PRINTED,
+    ];
+
+    let mut buf = Vec::with_capacity((fields.len() + 1) * 8);
+    for field in fields {
+        buf.extend_from_slice(&field.load(Ordering::Relaxed).to_le_bytes());
+    }
+    buf.extend_from_slice(&u64::from(output::truncated()).to_le_bytes());
+
+    buf
+}
@@ -0,0 +1,41 @@
+//! Values returned to the host by `eval_python`, `call_python`, `set_global_json`
+//! and `eval_json`.
+//!
+//! The `hl_*` functions in the C stubs encode the resulting Python value,
+//! or the text of the exception raised, and hand it over through [`hl_result_set`].
+//! The value encoding is decoded by `PyValue` on the host.
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use spin::Mutex;
+
+/// The call returned a value
+const STATUS_OK: u8 = 0;
+/// The call raised an exception
+const STATUS_EXCEPTION: u8 = 1;
+
+/// Encoded value or exception text of the last call
+static RESULT: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+
+/// Store the result of a call - called from C stubs
+#[unsafe(no_mangle)]
+pub extern "C" fn hl_result_set(data: *const u8, len: usize) {
+    let data = unsafe { core::slice::from_raw_parts(data, len) };
+    let mut result = RESULT.lock();
+
+    result.clear();
+    result.extend_from_slice(data);
+}
+
+/// Build the response to the host: a status byte followed by the encoded value
+/// if `success` is true, or by the exception text otherwise.
+pub fn response(success: bool) -> Vec<u8> {
+    let result = core::mem::take(&mut *RESULT.lock());
+
+    let mut response = Vec::with_capacity(result.len() + 1);
+    response.push(if success { STATUS_OK } else { STATUS_EXCEPTION });
+    response.extend_from_slice(&result);
+
+    response
+}